@@ -22,7 +22,7 @@
 #undef HAVE_LIBM
 
 /* Define to 1 if you have the `png' library (-lpng). */
-#undef HAVE_LIBPNG
+#define HAVE_LIBPNG 1
 
 /* Define to 1 if you have the `samplerate' library (-lsamplerate). */
 #undef HAVE_LIBSAMPLERATE
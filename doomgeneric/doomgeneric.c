@@ -6,6 +6,7 @@
 #include "doomgeneric.h"
 
 uint32_t* DG_ScreenBuffer = 0;
+uint32_t DG_ScreenPitch = DOOMGENERIC_RESX;
 
 void M_FindResponseFile(void);
 void D_DoomMain (void);
@@ -9,6 +9,9 @@
 
 
 extern uint32_t* DG_ScreenBuffer;
+// the distance between DG_ScreenBuffer rows in pixels, the platform may point
+// DG_ScreenBuffer straight into a wider framebuffer
+extern uint32_t DG_ScreenPitch;
 
 void doomgeneric_Create(int argc, char **argv);
 void doomgeneric_Tick();
@@ -20,6 +23,25 @@ void DG_DrawFrame();
 void DG_SleepMs(uint32_t ms);
 uint32_t DG_GetTicksMs();
 int DG_GetKey(int* pressed, unsigned char* key);
+// DG_GetKey plus the character the key typed in the current keyboard layout (0 if none),
+// the key itself is layout independent
+int DG_GetKeyEvent(int* pressed, unsigned char* key, unsigned char* typed);
+// whether the key is down right now, which can be ahead of the events doom has seen
+int DG_IsKeyHeld(unsigned char key);
+// the doom mouse button bits and the motion since the last call (dy points up),
+// returns 0 if nothing changed
+int DG_GetMouse(int* buttons, int* dx, int* dy);
+// the controller buttons and axes (-32768..32767, negative forward is forwards),
+// returns 0 if there is no controller, it is centered once after the controller goes away
+int DG_GetJoystick(int* buttons, int* turn, int* forward, int* strafe);
 void DG_SetWindowTitle(const char * title);
 
+// 8 bit fast path: the palette as XRGB8888 and the SCREENWIDTH x SCREENHEIGHT I_VideoBuffer,
+// DG_DrawIndexed returns 0 if DG_ScreenBuffer has to be filled and drawn with DG_DrawFrame instead
+void DG_SetPalette(const uint32_t* colors);
+int DG_DrawIndexed(const unsigned char* screen);
+
+// show the 80x25 text mode ENDOOM screen (character, attribute pairs) before quitting
+void DG_Endoom(const unsigned char* endoom);
+
 #endif //DOOM_GENERIC
@@ -21,6 +21,7 @@
 #include "config.h"
 #include "doomtype.h"
 #include "i_video.h"
+#include "doomgeneric.h"
 
 #ifdef ORIGCODE
 #include "txt_main.h"
@@ -76,6 +77,8 @@ void I_Endoom(byte *endoom_data)
     // Shut down text mode screen
 
     TXT_Shutdown();
+#else
+    DG_Endoom(endoom_data);
 #endif
 }
 
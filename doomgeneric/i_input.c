@@ -41,9 +41,10 @@
 
 #include "doomgeneric.h"
 
-int vanilla_keyboard_mapping = 1;
+int vanilla_keyboard_mapping = 0;
 
-// Is the shift key currently down?
+// Is the shift key currently down?  This follows the events as Doom
+// processes them, DG_IsKeyHeld can already be ahead of them.
 
 static int shiftdown = 0;
 
@@ -276,14 +277,31 @@ static void UpdateShiftStatus(int pressed, unsigned char key)
 }
 
 
+static int AccelerateMouse(int val)
+{
+    if (val < 0)
+        return -AccelerateMouse(-val);
+
+    if (val > mouse_threshold)
+    {
+        return (int)((val - mouse_threshold) * mouse_acceleration + mouse_threshold);
+    }
+    else
+    {
+        return val;
+    }
+}
+
 void I_GetEvent(void)
 {
     event_t event;
     int pressed;
     unsigned char key;
+    unsigned char typed;
+    int buttons, dx, dy;
 
     
-	while (DG_GetKey(&pressed, &key))
+	while (DG_GetKeyEvent(&pressed, &key, &typed))
     {
         UpdateShiftStatus(pressed, key);
 
@@ -295,7 +313,7 @@ void I_GetEvent(void)
             // (shift-translated, etc)
             event.type = ev_keydown;
             event.data1 = TranslateKey(key);
-            event.data2 = GetTypedChar(key);
+            event.data2 = typed != 0 ? typed : GetTypedChar(key);
 
             if (event.data1 != 0)
             {
@@ -323,16 +341,14 @@ void I_GetEvent(void)
         }
     }
 
-
-                /*
-            case SDL_MOUSEMOTION:
-                event.type = ev_mouse;
-                event.data1 = mouse_button_state;
-                event.data2 = AccelerateMouse(sdlevent.motion.xrel);
-                event.data3 = -AccelerateMouse(sdlevent.motion.yrel);
-                D_PostEvent(&event);
-                break;
-                */
+    if (DG_GetMouse(&buttons, &dx, &dy))
+    {
+        event.type = ev_mouse;
+        event.data1 = buttons;
+        event.data2 = AccelerateMouse(dx);
+        event.data3 = AccelerateMouse(dy);
+        D_PostEvent(&event);
+    }
 }
 
 void I_InitInput(void)
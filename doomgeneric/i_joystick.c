@@ -32,6 +32,8 @@
 #include "m_config.h"
 #include "m_misc.h"
 
+#include "doomgeneric.h"
+
 // When an axis is within the dead zone, it is set to zero.
 // This is 5% of the full range:
 
@@ -331,6 +333,20 @@ void I_UpdateJoystick(void)
         ev.data3 = GetAxisState(joystick_y_axis, joystick_y_invert);
         ev.data4 = GetAxisState(joystick_strafe_axis, joystick_strafe_invert);
 
+        D_PostEvent(&ev);
+    }
+#else
+    event_t ev;
+    int buttons, turn, forward, strafe;
+
+    if (DG_GetJoystick(&buttons, &turn, &forward, &strafe))
+    {
+        ev.type = ev_joystick;
+        ev.data1 = buttons;
+        ev.data2 = joystick_x_invert ? -turn : turn;
+        ev.data3 = joystick_y_invert ? -forward : forward;
+        ev.data4 = joystick_strafe_invert ? -strafe : strafe;
+
         D_PostEvent(&ev);
     }
 #endif
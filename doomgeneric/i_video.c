@@ -28,8 +28,10 @@ rcsid[] = "$Id: i_x.c,v 1.6 1997/02/03 22:45:10 b1 Exp $";
 #include "config.h"
 #include "v_video.h"
 #include "m_argv.h"
+#include "m_config.h"
 #include "d_event.h"
 #include "d_main.h"
+#include "i_joystick.h"
 #include "i_video.h"
 #include "z_zone.h"
 
@@ -115,6 +117,10 @@ int mouse_threshold = 10;
 
 int usegamma = 0;
 
+// Save screenshots as PNG (through src/png.rs) instead of PCX
+
+int png_screenshots = 1;
+
 typedef struct
 {
 	byte r;
@@ -240,6 +246,7 @@ void I_StartFrame (void)
 void I_StartTic (void)
 {
 	I_GetEvent();
+	I_UpdateJoystick();
 }
 
 void I_UpdateNoBlit (void)
@@ -256,6 +263,12 @@ void I_FinishUpdate (void)
     int x_offset, y_offset, x_offset_end;
     unsigned char *line_in, *line_out;
 
+    /* the platform can draw the 8 bit buffer itself, skipping the 32 bit conversion */
+    if (DG_DrawIndexed(I_VideoBuffer))
+    {
+        return;
+    }
+
     /* Offsets in case FB is bigger than DOOM */
     /* 600 = s_Fb heigt, 200 screenheight */
     /* 600 = s_Fb heigt, 200 screenheight */
@@ -287,6 +300,7 @@ void I_FinishUpdate (void)
             cmap_to_fb((void*)line_out, (void*)line_in, SCREENWIDTH);
 #endif
             line_out += (SCREENWIDTH * fb_scaling * (s_Fb.bits_per_pixel/8)) + x_offset_end;
+            line_out += (DG_ScreenPitch - s_Fb.xres) * (s_Fb.bits_per_pixel/8);
         }
         line_in += SCREENWIDTH;
     }
@@ -336,6 +350,8 @@ void I_SetPalette (byte* palette)
         colors[i].g = gammatable[usegamma][*palette++];
         colors[i].b = gammatable[usegamma][*palette++];
     }
+
+    DG_SetPalette((const uint32_t *) colors);
 }
 
 // Given an RGB value, find the closest matching palette index.
@@ -403,6 +419,8 @@ void I_EnableLoadingDisk(void)
 
 void I_BindVideoVariables (void)
 {
+    M_BindVariable("png_screenshots", &png_screenshots);
+    M_BindVariable("vanilla_keyboard_mapping", &vanilla_keyboard_mapping);
 }
 
 void I_DisplayFPSDots (boolean dots_on)
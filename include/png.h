@@ -0,0 +1,62 @@
+#pragma once
+
+#include "stdio.h"
+#include "stdint.h"
+
+// the subset of libpng that v_video.c uses, implemented in src/png.rs
+
+#define PNG_LIBPNG_VER_STRING "1.6.0"
+
+#define PNG_COLOR_TYPE_RGB 2
+#define PNG_COLOR_TYPE_PALETTE 3
+
+#define PNG_INTERLACE_NONE 0
+#define PNG_COMPRESSION_TYPE_DEFAULT 0
+#define PNG_FILTER_TYPE_DEFAULT 0
+
+typedef struct png_struct_def png_struct;
+typedef struct png_info_def png_info;
+
+typedef png_struct* png_structp;
+typedef png_struct** png_structpp;
+typedef png_info* png_infop;
+typedef png_info** png_infopp;
+
+typedef uint8_t png_byte;
+typedef png_byte* png_bytep;
+typedef uint32_t png_uint_32;
+typedef void* png_voidp;
+typedef const char* png_const_charp;
+
+typedef struct png_color_struct
+{
+    png_byte red;
+    png_byte green;
+    png_byte blue;
+} png_color;
+
+typedef png_color* png_colorp;
+
+typedef void (*png_error_ptr)(png_structp, png_const_charp);
+
+extern png_structp png_create_write_struct(png_const_charp user_png_ver, png_voidp error_ptr,
+                                           png_error_ptr error_fn, png_error_ptr warn_fn);
+
+extern png_infop png_create_info_struct(png_structp png_ptr);
+
+extern void png_destroy_write_struct(png_structpp png_ptr_ptr, png_infopp info_ptr_ptr);
+
+extern void png_init_io(png_structp png_ptr, FILE* fp);
+
+extern void png_set_IHDR(png_structp png_ptr, png_infop info_ptr, png_uint_32 width,
+                         png_uint_32 height, int bit_depth, int color_type, int interlace_method,
+                         int compression_method, int filter_method);
+
+extern void png_set_PLTE(png_structp png_ptr, png_infop info_ptr, png_colorp palette,
+                         int num_palette);
+
+extern void png_write_info(png_structp png_ptr, png_infop info_ptr);
+
+extern void png_write_row(png_structp png_ptr, png_bytep row);
+
+extern void png_write_end(png_structp png_ptr, png_infop info_ptr);
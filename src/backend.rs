@@ -0,0 +1,270 @@
+use core::{ptr::NonNull, slice};
+
+use alloc::{format, string::String, vec, vec::Vec};
+use libstd::{
+    eprintln,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    sys::{err::Error, map_file, timestamp, unmap_file},
+};
+
+use crate::{
+    fb::{parse_info, read_info_line, Framebuffer, PixelFormat},
+    png,
+};
+
+//
+
+// where the drawn frames end up
+pub enum Backend {
+    // `/dev/fb0` mapped into memory, frames are drawn straight into it,
+    // `saved` is what was on the screen before doom started
+    Fb {
+        fbo: File,
+        mapped: usize,
+        saved: Vec<u8>,
+        // the last seen /dev/fb0-info line and when it was read
+        info: String,
+        checked: u64,
+    },
+    // frames are drawn into memory and dumped as files, for running without a display
+    Headless(Headless),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    #[default]
+    Ppm,
+    Png,
+}
+
+pub struct Headless {
+    dir: String,
+    format: ImageFormat,
+    // write every nth frame
+    every: usize,
+    // stop after this many frames
+    limit: Option<usize>,
+    frame: usize,
+    line: Vec<u8>,
+}
+
+impl Backend {
+    pub fn framebuffer() -> (Self, Framebuffer<'static>) {
+        let line = read_info_line().expect("failed to read /dev/fb0-info");
+        let mut info =
+            parse_info(&line).unwrap_or_else(|| panic!("invalid /dev/fb0-info: {line:?}"));
+
+        let (fbo, buf) = map_fb();
+        let mapped = buf.as_ptr() as usize;
+        let saved = buf.to_vec();
+        info.buf = Some(buf);
+        info.clip_to_buf();
+
+        // keep the file open
+        let backend = Self::Fb {
+            fbo,
+            mapped,
+            saved,
+            info: line,
+            checked: now(),
+        };
+
+        (backend, info)
+    }
+
+    pub fn headless(
+        dir: String,
+        format: ImageFormat,
+        every: usize,
+        limit: Option<usize>,
+        (width, height): (usize, usize),
+    ) -> (Self, Framebuffer<'static>) {
+        let pitch = width * PixelFormat::XRGB8888.bytes_per_pixel();
+
+        let info = Framebuffer {
+            width,
+            height,
+            pitch,
+            format: PixelFormat::XRGB8888,
+            buf: Some(Vec::leak(vec![0u8; pitch * height])),
+        };
+
+        let backend = Self::Headless(Headless {
+            dir,
+            format,
+            every: every.max(1),
+            limit,
+            frame: 0,
+            line: Vec::new(),
+        });
+
+        (backend, info)
+    }
+
+    // called after each drawn frame, returns true once the backend wants the game to quit
+    pub fn present(&mut self, fb: &Framebuffer) -> bool {
+        match self {
+            Self::Fb { .. } => false,
+            Self::Headless(headless) => headless.present(fb),
+        }
+    }
+
+    // re-read /dev/fb0-info every now and then and remap /dev/fb0 if the mode changed,
+    // returns true if `fb` was replaced
+    pub fn poll_mode(&mut self, fb: &mut Framebuffer<'static>) -> bool {
+        let Self::Fb {
+            fbo,
+            mapped,
+            info,
+            checked,
+            ..
+        } = self
+        else {
+            return false;
+        };
+
+        let now = now();
+        if now.saturating_sub(*checked) < MODE_CHECK_NS {
+            return false;
+        }
+        *checked = now;
+
+        let Some(line) = read_info_line() else {
+            return false;
+        };
+        if line == *info {
+            return false;
+        }
+
+        let Some(mut new) = parse_info(&line) else {
+            eprintln!("ignoring invalid /dev/fb0-info: {line:?}");
+            *info = line;
+            return false;
+        };
+        *info = line;
+
+        // the size of the mapping depends on the mode, so map it again
+        fb.buf = None;
+        unmap_file(fbo.as_desc(), NonNull::new(*mapped as *mut ()).unwrap(), 0)
+            .expect("failed to unmap the fb");
+        let (new_fbo, buf) = map_fb();
+        *fbo = new_fbo;
+        *mapped = buf.as_ptr() as usize;
+
+        new.buf = Some(buf);
+        new.clip_to_buf();
+        *fb = new;
+        true
+    }
+
+    // put back the screen contents from before doom and release the framebuffer,
+    // `fb` can't be drawn into after this
+    pub fn close(self, fb: &mut Framebuffer) {
+        let buf = fb.buf.take();
+
+        match self {
+            Self::Fb {
+                fbo, mapped, saved, ..
+            } => {
+                // after a mode change the old contents don't fit anymore
+                if let Some(buf) = buf.filter(|buf| buf.len() == saved.len()) {
+                    buf.copy_from_slice(&saved);
+                }
+
+                unmap_file(fbo.as_desc(), NonNull::new(mapped as *mut ()).unwrap(), 0)
+                    .expect("failed to unmap the fb");
+            }
+            Self::Headless(_) => {}
+        }
+    }
+}
+
+// how often /dev/fb0-info is checked for mode changes
+const MODE_CHECK_NS: u64 = 1_000_000_000;
+
+fn now() -> u64 {
+    timestamp().unwrap() as u64
+}
+
+fn map_fb() -> (File, &'static mut [u8]) {
+    let fbo = OpenOptions::new()
+        .write(true)
+        .open("/dev/fb0")
+        .expect("failed to open /dev/fb0");
+    let meta = fbo.metadata().expect("failed to read fb file metadata");
+
+    let fbo_mapped = map_file(fbo.as_desc(), None, meta.len, 0).expect("failed to map the fb");
+    let buf = unsafe { slice::from_raw_parts_mut(fbo_mapped.as_ptr() as *mut u8, meta.len) };
+
+    (fbo, buf)
+}
+
+//
+
+impl Headless {
+    fn present(&mut self, fb: &Framebuffer) -> bool {
+        let frame = self.frame;
+        self.frame += 1;
+
+        if frame.is_multiple_of(self.every) {
+            let (ext, result) = match self.format {
+                ImageFormat::Ppm => ("ppm", self.ppm(fb)),
+                ImageFormat::Png => ("png", self.png(fb)),
+            };
+
+            let path = format!("{}/frame{frame:06}.{ext}", self.dir);
+            if let Err(err) = write_file(&path, &result) {
+                eprintln!("failed to write {path}: {err}");
+            }
+        }
+
+        self.limit.is_some_and(|limit| self.frame >= limit)
+    }
+
+    // binary PPM, the simplest image format there is
+    fn ppm(&mut self, fb: &Framebuffer) -> Vec<u8> {
+        let mut out = format!("P6\n{} {}\n255\n", fb.width, fb.height).into_bytes();
+        for row in rgb_rows(fb) {
+            out.extend(row);
+        }
+        out
+    }
+
+    fn png(&mut self, fb: &Framebuffer) -> Vec<u8> {
+        let mut encoder = png::Encoder::new(fb.width as u32, fb.height as u32, png::COLOR_TYPE_RGB);
+        let mut out = encoder.header();
+
+        for row in rgb_rows(fb) {
+            self.line.clear();
+            self.line.extend(row);
+            encoder.write_row(&self.line);
+        }
+
+        out.extend(encoder.finish());
+        out
+    }
+}
+
+// XRGB8888 little endian is B, G, R, X in memory
+fn rgb_rows<'a>(fb: &'a Framebuffer) -> impl Iterator<Item = impl Iterator<Item = u8> + 'a> + 'a {
+    let buf = fb.buf.as_ref().unwrap();
+    buf.chunks_exact(fb.pitch).take(fb.height).map(|row| {
+        row[..fb.width * 4]
+            .chunks_exact(4)
+            .flat_map(|px| [px[2], px[1], px[0]])
+    })
+}
+
+fn write_file(path: &str, data: &[u8]) -> Result<(), Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .create_dirs(true)
+        .truncate(true)
+        .open(path)?;
+    let mut file = BufWriter::new(file);
+
+    file.write_all(data)?;
+    file.flush()
+}
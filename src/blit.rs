@@ -0,0 +1,771 @@
+use core::mem;
+
+use alloc::{vec, vec::Vec};
+use libstd::{eprintln, println, sys::timestamp};
+
+use crate::{
+    color::Grade,
+    fb::{Framebuffer, PixelFormat},
+    filter::{self, Filter},
+};
+
+//
+
+// the area of the framebuffer the doom image is scaled into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    // the largest centered rect that fits in the framebuffer and keeps the image aspect ratio,
+    // the leftover is letterboxed/pillarboxed
+    pub fn fit(
+        fb_width: usize,
+        fb_height: usize,
+        src_width: usize,
+        src_height: usize,
+        aspect_correct: bool,
+    ) -> Self {
+        // 320x200 was shown on 4:3 screens, so the pixels were 20% taller than wide
+        let src_height = if aspect_correct {
+            src_height * 6 / 5
+        } else {
+            src_height
+        };
+
+        let (width, height) = if fb_width * src_height <= fb_height * src_width {
+            (fb_width, fb_width * src_height / src_width)
+        } else {
+            (fb_height * src_width / src_height, fb_height)
+        };
+
+        Self {
+            x: (fb_width - width) / 2,
+            y: (fb_height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    // swap the axes, the viewport is centered so it stays centered
+    pub fn transpose(self) -> Self {
+        Self {
+            x: self.y,
+            y: self.x,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    // a centered rect of exactly `num / den` times the image size, if it fits
+    pub fn scaled(
+        fb_width: usize,
+        fb_height: usize,
+        src_width: usize,
+        src_height: usize,
+        (num, den): (usize, usize),
+        aspect_correct: bool,
+    ) -> Option<Self> {
+        let width = src_width * num / den;
+        let mut height = src_height * num / den;
+        if aspect_correct {
+            height = height * 6 / 5;
+        }
+
+        (width != 0 && width <= fb_width && height != 0 && height <= fb_height).then_some(Self {
+            x: (fb_width - width) / 2,
+            y: (fb_height - height) / 2,
+            width,
+            height,
+        })
+    }
+
+    // the viewport for `scale`, falling back to smaller scales when it doesn't fit:
+    // the smaller integer scales, then half resolution and finally `fit`
+    pub fn choose(
+        fb_width: usize,
+        fb_height: usize,
+        src_width: usize,
+        src_height: usize,
+        scale: Scale,
+        aspect_correct: bool,
+    ) -> Self {
+        let fit = || Self::fit(fb_width, fb_height, src_width, src_height, aspect_correct);
+        let scaled = |factor| {
+            Self::scaled(
+                fb_width,
+                fb_height,
+                src_width,
+                src_height,
+                factor,
+                aspect_correct,
+            )
+        };
+
+        let wanted = match scale {
+            Scale::Fit => return fit(),
+            Scale::Times(n) => (n, 1),
+            Scale::Half => (1, 2),
+        };
+
+        if let Some(viewport) = scaled(wanted) {
+            return viewport;
+        }
+
+        let (viewport, used) = (1..wanted.0)
+            .rev()
+            .map(|n| (n, 1))
+            .chain([(1, 2)])
+            .filter(|&factor| factor != wanted)
+            .find_map(|factor| Some((scaled(factor)?, Some(factor))))
+            .unwrap_or_else(|| (fit(), None));
+
+        match used {
+            Some((num, 1)) => {
+                eprintln!("scale {scale:?} doesn't fit the framebuffer, using {num}x")
+            }
+            Some(_) => eprintln!("scale {scale:?} doesn't fit the framebuffer, using half"),
+            None => eprintln!("scale {scale:?} doesn't fit the framebuffer, using fit"),
+        }
+        viewport
+    }
+}
+
+// `-scale <fit|N|half>`, relative to the 640x400 DG_ScreenBuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scale {
+    // the largest size that fits, any factor
+    #[default]
+    Fit,
+    // an exact integer multiple
+    Times(usize),
+    // downscaled to 320x200
+    Half,
+}
+
+impl Scale {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "fit" => Some(Self::Fit),
+            "half" => Some(Self::Half),
+            _ => s
+                .trim_end_matches('x')
+                .parse()
+                .ok()
+                .filter(|&n| n != 0)
+                .map(Self::Times),
+        }
+    }
+}
+
+// `-rotate <0|90|180|270>`, clockwise, for rotated panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "0" => Some(Self::R0),
+            "90" => Some(Self::R90),
+            "180" => Some(Self::R180),
+            "270" => Some(Self::R270),
+            _ => None,
+        }
+    }
+
+    // width and height are swapped after the rotation
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Self::R90 | Self::R270)
+    }
+
+    // the size of a `width`x`height` image after the rotation
+    pub fn size(self, width: usize, height: usize) -> (usize, usize) {
+        if self.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    // rotate a `width`x`height` image into `dst`
+    pub fn apply<T: Copy + Default>(
+        self,
+        src: &[T],
+        width: usize,
+        height: usize,
+        dst: &mut Vec<T>,
+    ) {
+        dst.resize(width * height, T::default());
+        let (dst_width, _) = self.size(width, height);
+
+        match self {
+            Self::R0 => dst.copy_from_slice(src),
+            Self::R180 => {
+                dst.copy_from_slice(src);
+                dst.reverse();
+            }
+            Self::R90 => {
+                for (dy, row) in dst.chunks_exact_mut(dst_width).enumerate() {
+                    for (dx, px) in row.iter_mut().enumerate() {
+                        *px = src[dy + (height - 1 - dx) * width];
+                    }
+                }
+            }
+            Self::R270 => {
+                for (dy, row) in dst.chunks_exact_mut(dst_width).enumerate() {
+                    for (dx, px) in row.iter_mut().enumerate() {
+                        *px = src[(width - 1 - dy) + dx * width];
+                    }
+                }
+            }
+        }
+    }
+}
+
+//
+
+// scales the frame into the viewport with any (also non-integer) scale factor
+#[derive(Debug)]
+pub struct Scaler {
+    viewport: Viewport,
+    filter: Filter,
+    rotation: Rotation,
+    // the size of the frame after the rotation
+    src_width: usize,
+    src_height: usize,
+    // source column for each viewport column
+    x_map: Vec<usize>,
+    // source row for each viewport row
+    y_map: Vec<usize>,
+    // viewport rows drawn dimmed by the scanline filter,
+    // or columns when doom's rows are upright on the screen after the rotation
+    scanline: Vec<bool>,
+    dark_column: Vec<bool>,
+
+    // one source row converted to the framebuffer pixel format
+    row: Vec<u32>,
+    // one scaled row, ready to be copied into the framebuffer
+    line: Vec<u8>,
+    dark_line: Vec<u8>,
+
+    // the 320x200 frame and its filtered version for the smoothing filters
+    small: Vec<u32>,
+    filtered: Vec<u32>,
+    // horizontally interpolated source rows for bilinear, tagged with the source row
+    lerp_rows: [(usize, Vec<u32>); 2],
+
+    // color correction and the corrected frame
+    grade: Option<Grade>,
+    graded: Vec<u32>,
+    rotated: Vec<u32>,
+
+    // the XRGB8888 palette for `draw_indexed` and its framebuffer format versions,
+    // those are rebuilt only when the palette or the format changes
+    palette: [u32; 256],
+    palette_format: Option<PixelFormat>,
+    packed_palette: Vec<u32>,
+    dark_palette: Vec<u32>,
+    rotated_indexed: Vec<u8>,
+    prev_indexed: Vec<u8>,
+
+    // the previously presented frame, only the rows/columns that differ from it get redrawn
+    prev: Vec<u32>,
+    full_redraw: bool,
+}
+
+impl Scaler {
+    // `src_width` and `src_height` are the size of the frame before the rotation
+    pub fn new(
+        viewport: Viewport,
+        filter: Filter,
+        rotation: Rotation,
+        src_width: usize,
+        src_height: usize,
+    ) -> Self {
+        let (src_width, src_height) = rotation.size(src_width, src_height);
+        let mut scaler = Self {
+            viewport,
+            filter,
+            rotation,
+            src_width,
+            src_height,
+            x_map: Vec::new(),
+            y_map: Vec::new(),
+            scanline: Vec::new(),
+            dark_column: Vec::new(),
+            row: vec![0; src_width],
+            line: Vec::new(),
+            dark_line: Vec::new(),
+            small: Vec::new(),
+            filtered: Vec::new(),
+            lerp_rows: [(usize::MAX, Vec::new()), (usize::MAX, Vec::new())],
+            grade: None,
+            graded: Vec::new(),
+            rotated: Vec::new(),
+            palette: [0; 256],
+            palette_format: None,
+            packed_palette: Vec::new(),
+            dark_palette: Vec::new(),
+            rotated_indexed: Vec::new(),
+            prev_indexed: Vec::new(),
+            prev: Vec::new(),
+            full_redraw: true,
+        };
+        scaler.set_viewport(viewport);
+        scaler
+    }
+
+    // scale into a different area, like after a framebuffer mode change
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        // doom's own lines are 2 source rows, scanlines only make sense if they are 2+ rows tall
+        let scanlines = self.filter == Filter::Scanlines;
+        let dimmed = |len: usize, lines: usize| -> Vec<bool> {
+            (0..len)
+                .map(|d| scanlines && len >= 2 * lines && (2 * d * lines / len) % 2 == 1)
+                .collect()
+        };
+        if self.rotation.swaps_axes() {
+            self.scanline = vec![false; viewport.height];
+            self.dark_column = dimmed(viewport.width, self.src_width / 2);
+        } else {
+            self.scanline = dimmed(viewport.height, self.src_height / 2);
+            self.dark_column = vec![false; viewport.width];
+        }
+
+        self.viewport = viewport;
+        self.x_map = sample_map(viewport.width, self.src_width);
+        self.y_map = sample_map(viewport.height, self.src_height);
+        self.lerp_rows = [(usize::MAX, Vec::new()), (usize::MAX, Vec::new())];
+        self.full_redraw = true;
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    // redraw the whole viewport on the next `draw`, not just what changed
+    pub fn redraw(&mut self) {
+        self.full_redraw = true;
+    }
+
+    pub fn set_grade(&mut self, grade: Option<Grade>) {
+        self.grade = grade;
+        self.palette_format = None;
+        self.full_redraw = true;
+    }
+
+    // the palette `draw_indexed` uses, XRGB8888 like DG_ScreenBuffer
+    pub fn set_palette(&mut self, palette: &[u32; 256]) {
+        self.palette = *palette;
+        self.palette_format = None;
+    }
+
+    // drawing would be a plain copy of the frame, so doom could just as well draw into the framebuffer
+    pub fn is_passthrough(&self) -> bool {
+        self.filter == Filter::Nearest
+            && self.rotation == Rotation::R0
+            && self.grade.is_none()
+            && (self.viewport.width, self.viewport.height) == (self.src_width, self.src_height)
+    }
+
+    // the smoothing filters need the full color frame
+    pub fn supports_indexed(&self) -> bool {
+        matches!(self.filter, Filter::Nearest | Filter::Scanlines)
+    }
+
+    // the fast path: `src` is doom's 8 bit I_VideoBuffer at half the resolution of DG_ScreenBuffer,
+    // so the DG_ScreenBuffer maps are just halved and each pixel is a single palette lookup
+    pub fn draw_indexed(&mut self, fb: &mut Framebuffer, src: &[u8]) {
+        let format = fb.format;
+        if self.palette_format != Some(format) {
+            self.build_palette(format);
+        }
+
+        let (width, height) = (self.src_width / 2, self.src_height / 2);
+        let rotated = (self.rotation != Rotation::R0).then(|| {
+            let mut rotated = mem::take(&mut self.rotated_indexed);
+            let (unrotated_width, unrotated_height) = self.rotation.size(width, height);
+            self.rotation
+                .apply(src, unrotated_width, unrotated_height, &mut rotated);
+            rotated
+        });
+        let src = rotated.as_deref().unwrap_or(src);
+
+        // the other path's previous frame is stale now
+        self.prev.clear();
+        if self.prev_indexed.len() != src.len() {
+            self.prev_indexed = vec![0; src.len()];
+            self.full_redraw = true;
+        }
+
+        let bytes = format.bytes_per_pixel();
+        let x_offs = self.viewport.x * bytes;
+        self.line.resize(self.viewport.width * bytes, 0);
+        self.dark_line.resize(self.viewport.width * bytes, 0);
+
+        let pitch = fb.pitch;
+        let buf = fb.buf.as_mut().unwrap();
+
+        let mut last_sy = None;
+        let mut dirty = None;
+        for (dy, &sy) in self.y_map.iter().enumerate() {
+            let sy = sy / 2;
+            if last_sy != Some(sy) {
+                last_sy = Some(sy);
+                dirty = self.dirty_span_indexed(src, width, sy);
+
+                let Some((first, last)) = dirty else {
+                    continue;
+                };
+
+                let src_row = &src[sy * width..][..width];
+                let x_map = &self.x_map[first..last];
+
+                let line = &mut self.line[first * bytes..last * bytes];
+                for (dst, &sx) in line.chunks_exact_mut(bytes).zip(x_map) {
+                    format.write(dst, self.packed_palette[src_row[sx / 2] as usize]);
+                }
+
+                if self.filter == Filter::Scanlines && self.rotation.swaps_axes() {
+                    let dark = &self.dark_column[first..last];
+                    for ((dst, &sx), _) in line
+                        .chunks_exact_mut(bytes)
+                        .zip(x_map)
+                        .zip(dark)
+                        .filter(|(_, &dark)| dark)
+                    {
+                        format.write(dst, self.dark_palette[src_row[sx / 2] as usize]);
+                    }
+                } else if self.filter == Filter::Scanlines {
+                    let line = &mut self.dark_line[first * bytes..last * bytes];
+                    for (dst, &sx) in line.chunks_exact_mut(bytes).zip(x_map) {
+                        format.write(dst, self.dark_palette[src_row[sx / 2] as usize]);
+                    }
+                }
+            }
+
+            let Some((first, last)) = dirty else {
+                continue;
+            };
+
+            let line = if self.scanline[dy] {
+                &self.dark_line
+            } else {
+                &self.line
+            };
+
+            let spot = x_offs + (self.viewport.y + dy) * pitch;
+            buf[spot + first * bytes..spot + last * bytes]
+                .copy_from_slice(&line[first * bytes..last * bytes]);
+        }
+
+        self.prev_indexed.copy_from_slice(src);
+        if let Some(rotated) = rotated {
+            self.rotated_indexed = rotated;
+        }
+        self.full_redraw = false;
+    }
+
+    fn build_palette(&mut self, format: PixelFormat) {
+        let colors = self.palette.map(|px| match &self.grade {
+            Some(grade) => grade.apply(px),
+            None => px,
+        });
+
+        self.packed_palette.clear();
+        self.packed_palette
+            .extend(colors.iter().map(|&px| format.pack_xrgb(px)));
+        self.dark_palette.clear();
+        self.dark_palette.extend(
+            colors
+                .iter()
+                .map(|&px| format.pack_xrgb(filter::darken(px))),
+        );
+
+        self.palette_format = Some(format);
+        // the indices didn't change but what they look like did
+        self.full_redraw = true;
+    }
+
+    pub fn draw(&mut self, fb: &mut Framebuffer, src: &[u32]) {
+        // color correction runs before the filters, so they blend the corrected colors
+        let graded = self.grade.as_mut().map(|grade| {
+            let mut graded = mem::take(&mut self.graded);
+            grade.apply_frame(src, &mut graded);
+            graded
+        });
+        let src = graded.as_deref().unwrap_or(src);
+
+        let rotated = (self.rotation != Rotation::R0).then(|| {
+            let mut rotated = mem::take(&mut self.rotated);
+            let (width, height) = self.rotation.size(self.src_width, self.src_height);
+            self.rotation.apply(src, width, height, &mut rotated);
+            rotated
+        });
+        let src = rotated.as_deref().unwrap_or(src);
+
+        // the other path's previous frame is stale now
+        self.prev_indexed.clear();
+
+        let (small_width, small_height) = (self.src_width / 2, self.src_height / 2);
+
+        match self.filter {
+            Filter::Nearest | Filter::Scanlines => self.draw_nearest(fb, src),
+            Filter::Scale2x => {
+                self.small.resize(small_width * small_height, 0);
+                self.filtered.resize(self.src_width * self.src_height, 0);
+                filter::downsample_2x(src, self.src_width, &mut self.small);
+                filter::scale2x(&self.small, small_width, small_height, &mut self.filtered);
+
+                let filtered = mem::take(&mut self.filtered);
+                self.draw_nearest(fb, &filtered);
+                self.filtered = filtered;
+            }
+            Filter::Bilinear => {
+                self.small.resize(small_width * small_height, 0);
+                filter::downsample_2x(src, self.src_width, &mut self.small);
+
+                let small = mem::take(&mut self.small);
+                self.draw_bilinear(fb, &small, small_width, small_height);
+                self.small = small;
+            }
+        }
+
+        if let Some(graded) = graded {
+            self.graded = graded;
+        }
+        if let Some(rotated) = rotated {
+            self.rotated = rotated;
+        }
+        self.full_redraw = false;
+    }
+
+    fn draw_nearest(&mut self, fb: &mut Framebuffer, src: &[u32]) {
+        let format = fb.format;
+        let bytes = format.bytes_per_pixel();
+        let x_offs = self.viewport.x * bytes;
+        self.line.resize(self.viewport.width * bytes, 0);
+        self.dark_line.resize(self.viewport.width * bytes, 0);
+        self.track(src);
+
+        let pitch = fb.pitch;
+        let buf = fb.buf.as_mut().unwrap();
+
+        let mut last_sy = None;
+        let mut dirty = None;
+        for (dy, &sy) in self.y_map.iter().enumerate() {
+            // consecutive viewport rows mostly sample the same source row,
+            // so the conversion and horizontal scaling is done only once for them
+            if last_sy != Some(sy) {
+                last_sy = Some(sy);
+                dirty = self.dirty_span(src, sy);
+
+                let Some((first, last)) = dirty else {
+                    continue;
+                };
+
+                let src_row = &src[sy * self.src_width..][..self.src_width];
+                for (dst, &px) in self.row.iter_mut().zip(src_row) {
+                    *dst = format.pack_xrgb(px);
+                }
+
+                let line = &mut self.line[first * bytes..last * bytes];
+                for (dst, &sx) in line.chunks_exact_mut(bytes).zip(&self.x_map[first..last]) {
+                    format.write(dst, self.row[sx]);
+                }
+
+                if self.filter == Filter::Scanlines && self.rotation.swaps_axes() {
+                    let dark = &self.dark_column[first..last];
+                    for ((dst, &sx), _) in line
+                        .chunks_exact_mut(bytes)
+                        .zip(&self.x_map[first..last])
+                        .zip(dark)
+                        .filter(|(_, &dark)| dark)
+                    {
+                        format.write(dst, format.pack_xrgb(filter::darken(src_row[sx])));
+                    }
+                } else if self.filter == Filter::Scanlines {
+                    let line = &mut self.dark_line[first * bytes..last * bytes];
+                    for (dst, &sx) in line.chunks_exact_mut(bytes).zip(&self.x_map[first..last]) {
+                        format.write(dst, format.pack_xrgb(filter::darken(src_row[sx])));
+                    }
+                }
+            }
+
+            let Some((first, last)) = dirty else {
+                continue;
+            };
+
+            let line = if self.scanline[dy] {
+                &self.dark_line
+            } else {
+                &self.line
+            };
+
+            let spot = x_offs + (self.viewport.y + dy) * pitch;
+            buf[spot + first * bytes..spot + last * bytes]
+                .copy_from_slice(&line[first * bytes..last * bytes]);
+        }
+
+        self.prev.copy_from_slice(src);
+    }
+
+    fn draw_bilinear(&mut self, fb: &mut Framebuffer, src: &[u32], width: usize, height: usize) {
+        let format = fb.format;
+        let bytes = format.bytes_per_pixel();
+        let x_offs = self.viewport.x * bytes;
+        self.line.resize(self.viewport.width * bytes, 0);
+        self.track(src);
+
+        // every viewport pixel depends on 2 source rows and columns,
+        // so only whole viewport rows are skipped when their source rows did not change
+        let changed = src
+            .chunks_exact(width)
+            .zip(self.prev.chunks_exact(width))
+            .map(|(row, prev)| self.full_redraw || row != prev)
+            .collect::<Vec<bool>>();
+
+        let x_map = filter::lerp_map(self.viewport.width, width).collect::<Vec<_>>();
+
+        let pitch = fb.pitch;
+        let buf = fb.buf.as_mut().unwrap();
+
+        // the cached rows are from the last frame, their source rows may have changed since
+        for (tag, _) in &mut self.lerp_rows {
+            *tag = usize::MAX;
+        }
+
+        for (dy, (y0, y1, fy)) in filter::lerp_map(self.viewport.height, height).enumerate() {
+            if !changed[y0] && !changed[y1] {
+                continue;
+            }
+
+            for (slot, sy) in [(0, y0), (1, y1)] {
+                // the previous viewport row usually used the same source rows
+                if let Some(other) = self.lerp_rows[slot..].iter().position(|(t, _)| *t == sy) {
+                    self.lerp_rows.swap(slot, slot + other);
+                    continue;
+                }
+
+                let src_row = &src[sy * width..][..width];
+                let (tag, row) = &mut self.lerp_rows[slot];
+                *tag = sy;
+                row.clear();
+                row.extend(
+                    x_map
+                        .iter()
+                        .map(|&(x0, x1, fx)| filter::lerp(src_row[x0], src_row[x1], fx)),
+                );
+            }
+
+            let [(_, top), (_, bottom)] = &self.lerp_rows;
+            for ((dst, &a), &b) in self.line.chunks_exact_mut(bytes).zip(top).zip(bottom) {
+                format.write(dst, format.pack_xrgb(filter::lerp(a, b, fy)));
+            }
+
+            let spot = x_offs + (self.viewport.y + dy) * pitch;
+            buf[spot..spot + self.line.len()].copy_from_slice(&self.line);
+        }
+
+        self.prev.copy_from_slice(src);
+    }
+
+    // the tracked frame size depends on the filter
+    fn track(&mut self, src: &[u32]) {
+        if self.prev.len() != src.len() {
+            self.prev = vec![0; src.len()];
+            self.full_redraw = true;
+        }
+    }
+
+    // the viewport columns `first..last` that changed in source row `sy` since the last frame
+    fn dirty_span(&self, src: &[u32], sy: usize) -> Option<(usize, usize)> {
+        if self.full_redraw {
+            return Some((0, self.viewport.width));
+        }
+
+        let row = &src[sy * self.src_width..][..self.src_width];
+        let prev = &self.prev[sy * self.src_width..][..self.src_width];
+
+        let first = row.iter().zip(prev).position(|(a, b)| a != b)?;
+        let last = row.iter().zip(prev).rposition(|(a, b)| a != b)?;
+
+        let first = self.x_map.partition_point(|&sx| sx < first);
+        let last = self.x_map.partition_point(|&sx| sx <= last);
+        (first != last).then_some((first, last))
+    }
+
+    // like `dirty_span`, for the `width` wide 8 bit frame
+    fn dirty_span_indexed(&self, src: &[u8], width: usize, sy: usize) -> Option<(usize, usize)> {
+        if self.full_redraw {
+            return Some((0, self.viewport.width));
+        }
+
+        let row = &src[sy * width..][..width];
+        let prev = &self.prev_indexed[sy * width..][..width];
+
+        let first = row.iter().zip(prev).position(|(a, b)| a != b)?;
+        let last = row.iter().zip(prev).rposition(|(a, b)| a != b)?;
+
+        let first = self.x_map.partition_point(|&sx| sx / 2 < first);
+        let last = self.x_map.partition_point(|&sx| sx / 2 <= last);
+        (first != last).then_some((first, last))
+    }
+
+    // the old blitter, only kept around to compare against in `bench`
+    fn draw_per_pixel(&self, fb: &mut Framebuffer, src: &[u32]) {
+        for (dy, &sy) in self.y_map.iter().enumerate() {
+            let row = &src[sy * self.src_width..][..self.src_width];
+
+            for (dx, &sx) in self.x_map.iter().enumerate() {
+                let px = fb.format.pack_xrgb(row[sx]);
+                fb.fill_packed(self.viewport.x + dx, self.viewport.y + dy, 1, 1, px);
+            }
+        }
+    }
+}
+
+// sample from the center of each destination pixel
+fn sample_map(dst_len: usize, src_len: usize) -> Vec<usize> {
+    (0..dst_len)
+        .map(|d| ((2 * d + 1) * src_len / (2 * dst_len)).min(src_len - 1))
+        .collect()
+}
+
+//
+
+// `-blitbench`: time the per-pixel and the row based blitters on the real framebuffer
+pub fn bench(scaler: &mut Scaler, fb: &mut Framebuffer, src: &[u32]) {
+    const FRAMES: u64 = 100;
+
+    let start = timestamp().unwrap();
+    for _ in 0..FRAMES {
+        scaler.draw_per_pixel(fb, src);
+    }
+    let per_pixel = (timestamp().unwrap() - start) as u64 / FRAMES;
+
+    let start = timestamp().unwrap();
+    for _ in 0..FRAMES {
+        scaler.redraw();
+        scaler.draw(fb, src);
+    }
+    let rows = (timestamp().unwrap() - start) as u64 / FRAMES;
+
+    println!(
+        "blitbench: {:?} {FRAMES} frames, per-pixel: {}us/frame, rows: {}us/frame ({}x)",
+        scaler.viewport,
+        per_pixel / 1_000,
+        rows / 1_000,
+        per_pixel as f32 / rows.max(1) as f32,
+    );
+}
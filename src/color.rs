@@ -0,0 +1,329 @@
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::fs;
+
+//
+
+// color correction applied to DG_ScreenBuffer before it is scaled
+//
+// brightness, contrast and gamma are baked into per channel curves,
+// then saturation is applied and finally the optional .cube LUT
+#[derive(Debug, Clone)]
+pub struct Grade {
+    curves: [[u8; 256]; 3],
+    saturation: f32,
+    lut: Option<Lut>,
+    // a doom frame has at most 256 different colors, so the graded colors are cached
+    cache: Vec<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GradeParams {
+    // >1 brightens the midtones
+    pub gamma: f32,
+    // added to every channel, -1.0..1.0
+    pub brightness: f32,
+    // scales the distance from mid gray
+    pub contrast: f32,
+    // 0 is grayscale, 1 is unchanged
+    pub saturation: f32,
+}
+
+impl GradeParams {
+    pub const NEUTRAL: Self = Self {
+        gamma: 1.0,
+        brightness: 0.0,
+        contrast: 1.0,
+        saturation: 1.0,
+    };
+}
+
+const CACHE_SIZE: usize = 4096;
+// the unused top byte is masked off the keys, so this can't be a pixel
+const EMPTY: u32 = u32::MAX;
+
+impl Grade {
+    pub fn new(params: GradeParams, lut: Option<Lut>) -> Self {
+        let mut curve = [0u8; 256];
+        for (i, out) in curve.iter_mut().enumerate() {
+            let v = i as f32 / 255.0;
+            let v = (v - 0.5) * params.contrast + 0.5 + params.brightness;
+            let v = libm::powf(v.clamp(0.0, 1.0), 1.0 / params.gamma.max(0.01));
+            *out = (v * 255.0 + 0.5) as u8;
+        }
+
+        Self {
+            curves: [curve; 3],
+            saturation: params.saturation,
+            lut,
+            cache: vec![(EMPTY, 0); CACHE_SIZE],
+        }
+    }
+
+    // grade a whole XRGB8888 frame
+    pub fn apply_frame(&mut self, src: &[u32], dst: &mut Vec<u32>) {
+        dst.clear();
+        dst.extend(src.iter().map(|&px| self.apply_cached(px)));
+    }
+
+    fn apply_cached(&mut self, px: u32) -> u32 {
+        let px = px & 0x00ff_ffff;
+        let slot = ((px ^ (px >> 12) ^ (px >> 20)) as usize) % CACHE_SIZE;
+        let (key, graded) = self.cache[slot];
+        if key == px {
+            return graded;
+        }
+
+        let graded = self.apply(px);
+        self.cache[slot] = (px, graded);
+        graded
+    }
+
+    pub fn apply(&self, px: u32) -> u32 {
+        let [b, g, r, _] = px.to_le_bytes();
+        let (r, g, b) = (
+            self.curves[0][r as usize],
+            self.curves[1][g as usize],
+            self.curves[2][b as usize],
+        );
+
+        let mut rgb = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0];
+
+        if self.saturation != 1.0 {
+            let luma = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+            for c in &mut rgb {
+                *c = (luma + (*c - luma) * self.saturation).clamp(0.0, 1.0);
+            }
+        }
+
+        if let Some(lut) = &self.lut {
+            rgb = lut.apply(rgb);
+        }
+
+        let [r, g, b] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8);
+        u32::from_le_bytes([b, g, r, 0])
+    }
+}
+
+//
+
+// an Adobe/Resolve .cube LUT
+#[derive(Debug, Clone)]
+pub struct Lut {
+    // the inputs the first and last table entries are for, per channel
+    min: [f32; 3],
+    max: [f32; 3],
+    table: Table,
+}
+
+#[derive(Debug, Clone)]
+enum Table {
+    OneD(Vec<[f32; 3]>),
+    // red changes the fastest
+    ThreeD { size: usize, table: Vec<[f32; 3]> },
+}
+
+impl Lut {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_text(path)?;
+        Self::parse(&text).map_err(|err| format!("{path}: {err}"))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut size_1d = None;
+        let mut size_3d = None;
+        let mut min = [0.0f32; 3];
+        let mut max = [1.0f32; 3];
+        let mut table = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let word = words.next().unwrap_or_default();
+            let mut values = |n: usize| -> Result<[f32; 3], String> {
+                let mut out = [0.0f32; 3];
+                let mut count = 0;
+                for c in words.by_ref() {
+                    if count >= n {
+                        return Err(format!("line {}: too many values", i + 1));
+                    }
+                    out[count] = c
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid value {c:?}", i + 1))?;
+                    count += 1;
+                }
+                if count < n {
+                    return Err(format!("line {}: missing values", i + 1));
+                }
+                Ok(out)
+            };
+
+            match word {
+                "TITLE" => {}
+                "DOMAIN_MIN" => min = values(3)?,
+                "DOMAIN_MAX" => max = values(3)?,
+                // the older form, one range for all channels
+                "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                    let [lo, hi, _] = values(2)?;
+                    min = [lo; 3];
+                    max = [hi; 3];
+                }
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    let size = words
+                        .next()
+                        .and_then(|s| s.parse::<usize>().ok())
+                        .filter(|&size| size >= 2)
+                        .ok_or_else(|| format!("line {}: invalid {word}", i + 1))?;
+
+                    if word == "LUT_1D_SIZE" {
+                        size_1d = Some(size);
+                    } else {
+                        size_3d = Some(size);
+                    }
+                }
+                _ => {
+                    let mut rgb = [0.0f32; 3];
+                    for (n, c) in [word].into_iter().chain(words).enumerate() {
+                        if n >= 3 {
+                            return Err(format!("line {}: too many values", i + 1));
+                        }
+                        rgb[n] = c
+                            .parse()
+                            .map_err(|_| format!("line {}: invalid value {c:?}", i + 1))?;
+                    }
+                    table.push(rgb);
+                }
+            }
+        }
+
+        if (0..3).any(|ch| max[ch] <= min[ch]) {
+            return Err("empty input range".into());
+        }
+
+        let table = match (size_1d, size_3d) {
+            (Some(size), None) if table.len() == size => Table::OneD(table),
+            (None, Some(size)) if table.len() == size * size * size => {
+                Table::ThreeD { size, table }
+            }
+            (None, None) => return Err("missing LUT_1D_SIZE or LUT_3D_SIZE".into()),
+            _ => return Err(format!("wrong number of entries ({})", table.len())),
+        };
+
+        Ok(Self { min, max, table })
+    }
+
+    fn apply(&self, mut rgb: [f32; 3]) -> [f32; 3] {
+        // where in the input range each channel is
+        for (ch, c) in rgb.iter_mut().enumerate() {
+            *c = (*c - self.min[ch]) / (self.max[ch] - self.min[ch]);
+        }
+
+        match &self.table {
+            Table::OneD(table) => {
+                let mut out = [0.0; 3];
+                for (ch, out) in out.iter_mut().enumerate() {
+                    let (i0, i1, f) = lerp_index(rgb[ch], table.len());
+                    *out = table[i0][ch] + (table[i1][ch] - table[i0][ch]) * f;
+                }
+                out
+            }
+            Table::ThreeD { size, table } => {
+                let (r0, r1, fr) = lerp_index(rgb[0], *size);
+                let (g0, g1, fg) = lerp_index(rgb[1], *size);
+                let (b0, b1, fb) = lerp_index(rgb[2], *size);
+                let at = |r: usize, g: usize, b: usize| table[r + g * size + b * size * size];
+                let mix = |a: [f32; 3], b: [f32; 3], f: f32| {
+                    [
+                        a[0] + (b[0] - a[0]) * f,
+                        a[1] + (b[1] - a[1]) * f,
+                        a[2] + (b[2] - a[2]) * f,
+                    ]
+                };
+
+                // trilinear
+                let c00 = mix(at(r0, g0, b0), at(r1, g0, b0), fr);
+                let c10 = mix(at(r0, g1, b0), at(r1, g1, b0), fr);
+                let c01 = mix(at(r0, g0, b1), at(r1, g0, b1), fr);
+                let c11 = mix(at(r0, g1, b1), at(r1, g1, b1), fr);
+                mix(mix(c00, c10, fg), mix(c01, c11, fg), fb)
+            }
+        }
+    }
+}
+
+// the 2 table entries around `v` and the weight of the second one
+fn lerp_index(v: f32, len: usize) -> (usize, usize, f32) {
+    let pos = v.clamp(0.0, 1.0) * (len - 1) as f32;
+    let i0 = (pos as usize).min(len - 1);
+    let i1 = (i0 + 1).min(len - 1);
+    (i0, i1, pos - i0 as f32)
+}
+
+fn _lut_assert(text: &str, input: [f32; 3], expected: [f32; 3]) {
+    let lut = Lut::parse(text).unwrap_or_else(|err| panic!("{text:?} => {err}"));
+    let val = lut.apply(input);
+    assert!(
+        val.iter().zip(expected).all(|(v, e)| (v - e).abs() < 1e-4),
+        "{text:?} at {input:?} => {val:?}, expected: {expected:?}"
+    );
+}
+
+fn _lut_err_assert(text: &str, expected: &str) {
+    let err = Lut::parse(text).err();
+    assert_eq!(
+        err.as_deref(),
+        Some(expected),
+        "{text:?} => {err:?}, expected: {expected}"
+    );
+}
+
+pub fn _lut_parse_test() {
+    let identity_3d = "LUT_3D_SIZE 2\n\
+                       0 0 0\n1 0 0\n0 1 0\n1 1 0\n\
+                       0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+    _lut_assert(identity_3d, [0.25, 0.5, 0.75], [0.25, 0.5, 0.75]);
+    _lut_assert(
+        "# inverted\nTITLE \"x\"\nLUT_1D_SIZE 2\n1 1 1\n0 0 0\n",
+        [0.25, 0.5, 1.0],
+        [0.75, 0.5, 0.0],
+    );
+    _lut_assert(
+        "LUT_1D_SIZE 2\nLUT_1D_INPUT_RANGE 0 2\n0 0 0\n1 1 1\n",
+        [1.0, 0.5, 2.0],
+        [0.5, 0.25, 1.0],
+    );
+    _lut_assert(
+        "LUT_1D_SIZE 2\nDOMAIN_MIN 0 0 1\nDOMAIN_MAX 1 2 2\n0 0 0\n1 1 1\n",
+        [0.5, 0.5, 1.5],
+        [0.5, 0.25, 0.5],
+    );
+
+    _lut_err_assert("0 0 0\n1 1 1\n", "missing LUT_1D_SIZE or LUT_3D_SIZE");
+    _lut_err_assert(
+        "LUT_1D_SIZE 3\n0 0 0\n1 1 1\n",
+        "wrong number of entries (2)",
+    );
+    _lut_err_assert(
+        "LUT_3D_SIZE 2\n0 0 0\n1 1 1\n",
+        "wrong number of entries (2)",
+    );
+    _lut_err_assert(
+        "LUT_1D_SIZE 2\nLUT_3D_SIZE 2\n0 0 0\n1 1 1\n",
+        "wrong number of entries (2)",
+    );
+    _lut_err_assert("LUT_1D_SIZE 1\n0 0 0\n", "line 1: invalid LUT_1D_SIZE");
+    _lut_err_assert(
+        "LUT_1D_SIZE 2\n0 0 0\n1 x 1\n",
+        "line 3: invalid value \"x\"",
+    );
+    _lut_err_assert("LUT_1D_SIZE 2\n0 0 0 0\n", "line 2: too many values");
+    _lut_err_assert("DOMAIN_MIN 0 0\n", "line 1: missing values");
+    _lut_err_assert(
+        "LUT_3D_INPUT_RANGE 1 1\nLUT_3D_SIZE 2\n",
+        "empty input range",
+    );
+}
@@ -0,0 +1,141 @@
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use libstd::{
+    eprintln,
+    fs::OpenOptions,
+    io::{stdin, Read},
+    sys::nanosleep,
+    thread::spawn,
+};
+
+//
+
+// the wait after an error or EOF doubles each time, up to the max
+const MIN_BACKOFF_NS: u64 = 10_000_000;
+const MAX_BACKOFF_NS: u64 = 1_000_000_000;
+
+// json lines longer than this are garbage, not a partial event
+const MAX_LINE: usize = 4096;
+
+//
+
+// what a device is read into
+pub trait Reader: Send + 'static {
+    // every chunk as it comes in
+    fn read(&mut self, data: &[u8]);
+
+    // the stream ended, anything it was holding down has to be let go
+    fn closed(&mut self);
+}
+
+// read `device` (stdin if none) in the background
+//
+// the device is reopened after EOF, so whatever feeds it can restart without restarting doom,
+// an `optional` device that can't be opened at all is left alone without a message
+pub fn spawn_reader(
+    name: &'static str,
+    device: Option<String>,
+    optional: bool,
+    mut reader: impl Reader,
+) {
+    spawn(move || {
+        let path = device.as_deref().unwrap_or("stdin");
+        let mut backoff = Backoff::new();
+        let mut opened = false;
+        let mut chunk = [0u8; 256];
+
+        loop {
+            let mut source = match open(device.as_deref()) {
+                Ok(source) => source,
+                Err(_) if optional && !opened => return,
+                Err(err) => {
+                    if backoff.is_first() {
+                        eprintln!("{name}: {err}");
+                    }
+                    backoff.wait();
+                    continue;
+                }
+            };
+            opened = true;
+
+            loop {
+                match source.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        backoff.reset();
+                        reader.read(&chunk[..n]);
+                    }
+                    Err(err) => {
+                        if backoff.is_first() {
+                            eprintln!("{name}: failed to read {path}: {err}");
+                        }
+                        backoff.wait();
+                    }
+                }
+            }
+
+            if backoff.is_first() {
+                eprintln!("{name}: {path} closed");
+            }
+            reader.closed();
+            backoff.wait();
+        }
+    });
+}
+
+fn open(device: Option<&str>) -> Result<Box<dyn Read>, String> {
+    match device {
+        Some(path) => OpenOptions::new()
+            .read(true)
+            .open(path)
+            .map(|file| Box::new(file) as Box<dyn Read>)
+            .map_err(|err| format!("failed to open {path}: {err}")),
+        None => Ok(Box::new(stdin().lock())),
+    }
+}
+
+// move every complete line out of `pending`, blank lines are skipped
+pub fn lines(name: &str, pending: &mut Vec<u8>, mut f: impl FnMut(&str)) {
+    let mut at = 0;
+    while let Some(len) = pending[at..].iter().position(|&b| b == b'\n') {
+        let line = &pending[at..at + len];
+        at += len + 1;
+
+        let line = core::str::from_utf8(line).unwrap_or_default().trim();
+        if !line.is_empty() {
+            f(line);
+        }
+    }
+
+    if pending.len() - at > MAX_LINE {
+        eprintln!("{name}: dropping a {} byte line", pending.len() - at);
+        at = pending.len();
+    }
+
+    pending.drain(..at);
+}
+
+//
+
+struct Backoff {
+    ns: u64,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self { ns: MIN_BACKOFF_NS }
+    }
+
+    // errors are only worth printing once per streak
+    fn is_first(&self) -> bool {
+        self.ns == MIN_BACKOFF_NS
+    }
+
+    fn wait(&mut self) {
+        nanosleep(self.ns);
+        self.ns = (self.ns * 2).min(MAX_BACKOFF_NS);
+    }
+
+    fn reset(&mut self) {
+        self.ns = MIN_BACKOFF_NS;
+    }
+}
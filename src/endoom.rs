@@ -0,0 +1,82 @@
+use alloc::{format, string::String};
+use libstd::print;
+
+use crate::cp437;
+
+//
+
+// the ENDOOM lump is a VGA text mode screen: 80x25 cells of (character, attribute)
+pub const COLUMNS: usize = 80;
+pub const ROWS: usize = 25;
+pub const SIZE: usize = COLUMNS * ROWS * 2;
+
+// the cells are the size of the 8x16 VGA font, so the screen is 640x400
+pub const CELL_WIDTH: usize = 8;
+pub const CELL_HEIGHT: usize = 16;
+pub const WIDTH: usize = COLUMNS * CELL_WIDTH;
+pub const HEIGHT: usize = ROWS * CELL_HEIGHT;
+
+// how long the blinking text stays on/off
+pub const BLINK_NS: u64 = 267_000_000;
+// quit anyway if no key is pressed, so unattended setups don't hang here
+pub const TIMEOUT_NS: u64 = 10_000_000_000;
+
+// the 16 color text mode palette, XRGB8888
+const PALETTE: [u32; 16] = [
+    0x000000, 0x0000aa, 0x00aa00, 0x00aaaa, 0xaa0000, 0xaa00aa, 0xaa5500, 0xaaaaaa, //
+    0x555555, 0x5555ff, 0x55ff55, 0x55ffff, 0xff5555, 0xff55ff, 0xffff55, 0xffffff,
+];
+
+// the ANSI color for each VGA color (VGA is BGR ordered, ANSI is RGB ordered)
+const ANSI: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+//
+
+// the foreground, background and whether the cell blinks
+fn attribute(attr: u8) -> (usize, usize, bool) {
+    (
+        (attr & 0xf) as usize,
+        ((attr >> 4) & 0x7) as usize,
+        attr & 0x80 != 0,
+    )
+}
+
+// draw the screen into a WIDTH x HEIGHT XRGB8888 buffer,
+// blinking cells show only their background while `blink_off`
+pub fn render(data: &[u8], blink_off: bool, dst: &mut [u32]) {
+    for (i, cell) in data.chunks_exact(2).take(COLUMNS * ROWS).enumerate() {
+        let (col, row) = (i % COLUMNS, i / COLUMNS);
+        let (fg, bg, blink) = attribute(cell[1]);
+        let glyph = &cp437::FONT[cell[0] as usize];
+        let fg = if blink && blink_off { bg } else { fg };
+
+        for y in 0..CELL_HEIGHT {
+            let bits = glyph[y];
+            let line = &mut dst[col * CELL_WIDTH + (row * CELL_HEIGHT + y) * WIDTH..][..CELL_WIDTH];
+            for (x, px) in line.iter_mut().enumerate() {
+                let on = bits & (0x80 >> x) != 0;
+                *px = PALETTE[if on { fg } else { bg }];
+            }
+        }
+    }
+}
+
+// print the screen with ANSI colors, for running without a display
+pub fn print(data: &[u8]) {
+    let mut out = String::new();
+    for row in data.chunks_exact(COLUMNS * 2).take(ROWS) {
+        let mut last = None;
+        for cell in row.chunks_exact(2) {
+            let (fg, bg, blink) = attribute(cell[1]);
+            if last != Some(cell[1]) {
+                last = Some(cell[1]);
+                let bright = if fg >= 8 { 90 } else { 30 };
+                let blink = if blink { ";5" } else { "" };
+                out += &format!("\x1b[0;{};{}{blink}m", bright + ANSI[fg % 8], 40 + ANSI[bg]);
+            }
+            out.push(cp437::CHARS[cell[0] as usize]);
+        }
+        out += "\x1b[0m\n";
+    }
+    print!("{out}");
+}
@@ -0,0 +1,201 @@
+use alloc::{string::String, vec::Vec};
+use hyperion_color::Color;
+use libstd::{eprintln, fs::OpenOptions, io::BufReader};
+
+//
+
+pub fn read_info_line() -> Option<String> {
+    let fbo_info = OpenOptions::new().read(true).open("/dev/fb0-info").ok()?;
+    let mut fbo_info = BufReader::new(fbo_info);
+
+    let mut buf = String::new();
+    fbo_info.read_line(&mut buf).ok()?;
+    Some(buf)
+}
+
+// `/dev/fb0-info` is a single line: `width:height:pitch[:bpp[:layout]]`
+//
+// layout is either `rgb`/`bgr` (which channel sits in the most significant bits)
+// or 3 explicit `offset/length` bitfields for red, green and blue: `11/5:5/6:0/5`
+pub fn parse_info(line: &str) -> Option<Framebuffer<'static>> {
+    let mut fbo_info_iter = line.trim().split(':');
+    let width = fbo_info_iter.next()?.parse::<usize>().ok()?;
+    let height = fbo_info_iter.next()?.parse::<usize>().ok()?;
+    let pitch = fbo_info_iter.next()?.parse::<usize>().ok()?;
+    let bpp = match fbo_info_iter.next() {
+        Some(bpp) => bpp.parse::<usize>().ok()?,
+        None => 32,
+    };
+
+    let layout = fbo_info_iter.collect::<Vec<&str>>();
+    let format = match layout.as_slice() {
+        [] | ["rgb"] => PixelFormat::rgb(bpp),
+        ["bgr"] => PixelFormat::bgr(bpp),
+        [r, g, b] => PixelFormat {
+            bpp,
+            red: BitField::parse(r)?,
+            green: BitField::parse(g)?,
+            blue: BitField::parse(b)?,
+        },
+        _ => return None,
+    };
+
+    Some(Framebuffer {
+        width,
+        height,
+        pitch,
+        format,
+        buf: None,
+    })
+}
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitField {
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl BitField {
+    pub const fn new(offset: u32, length: u32) -> Self {
+        Self { offset, length }
+    }
+
+    // `offset/length`
+    fn parse(s: &str) -> Option<Self> {
+        let (offset, length) = s.split_once('/')?;
+        Some(Self::new(offset.parse().ok()?, length.parse().ok()?))
+    }
+
+    // keep the `length` most significant bits of the 8 bit channel
+    fn pack(self, channel: u8) -> u32 {
+        ((channel as u32) >> (8 - self.length.min(8))) << self.offset
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub bpp: usize,
+    pub red: BitField,
+    pub green: BitField,
+    pub blue: BitField,
+}
+
+impl PixelFormat {
+    // XRGB8888, same as DG_ScreenBuffer
+    pub const XRGB8888: Self = Self::rgb(32);
+
+    // red in the most significant bits
+    pub const fn rgb(bpp: usize) -> Self {
+        match bpp {
+            15 => Self::new(
+                bpp,
+                BitField::new(10, 5),
+                BitField::new(5, 5),
+                BitField::new(0, 5),
+            ),
+            16 => Self::new(
+                bpp,
+                BitField::new(11, 5),
+                BitField::new(5, 6),
+                BitField::new(0, 5),
+            ),
+            _ => Self::new(
+                bpp,
+                BitField::new(16, 8),
+                BitField::new(8, 8),
+                BitField::new(0, 8),
+            ),
+        }
+    }
+
+    // blue in the most significant bits
+    pub const fn bgr(bpp: usize) -> Self {
+        let rgb = Self::rgb(bpp);
+        Self::new(bpp, rgb.blue, rgb.green, rgb.red)
+    }
+
+    pub const fn new(bpp: usize, red: BitField, green: BitField, blue: BitField) -> Self {
+        Self {
+            bpp,
+            red,
+            green,
+            blue,
+        }
+    }
+
+    pub const fn bytes_per_pixel(&self) -> usize {
+        self.bpp.div_ceil(8)
+    }
+
+    pub fn pack(&self, r: u8, g: u8, b: u8) -> u32 {
+        self.red.pack(r) | self.green.pack(g) | self.blue.pack(b)
+    }
+
+    // convert a DG_ScreenBuffer pixel (XRGB8888)
+    pub fn pack_xrgb(&self, px: u32) -> u32 {
+        let [b, g, r, _] = px.to_le_bytes();
+        self.pack(r, g, b)
+    }
+
+    // write one packed pixel, `dst` is exactly `bytes_per_pixel` long
+    pub fn write(&self, dst: &mut [u8], px: u32) {
+        dst.copy_from_slice(&px.to_le_bytes()[..dst.len()]);
+    }
+}
+
+//
+
+#[derive(Debug)]
+pub struct Framebuffer<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub pitch: usize,
+    pub format: PixelFormat,
+    pub buf: Option<&'a mut [u8]>,
+}
+
+impl Framebuffer<'_> {
+    pub fn fill(&mut self, x: usize, y: usize, w: usize, h: usize, color: Color) {
+        self.fill_packed(x, y, w, h, self.format.pack(color.r, color.g, color.b));
+    }
+
+    // the rect is clipped to the framebuffer
+    pub fn fill_packed(&mut self, x: usize, y: usize, w: usize, h: usize, px: u32) {
+        let format = self.format;
+        let bytes = format.bytes_per_pixel();
+        let w = w.min(self.width.saturating_sub(x));
+        let h = h.min(self.height.saturating_sub(y));
+
+        for yd in y..y + h {
+            let spot = x * bytes + yd * self.pitch;
+            for dst in self.buf.as_mut().unwrap()[spot..spot + bytes * w].chunks_exact_mut(bytes) {
+                format.write(dst, px);
+            }
+        }
+    }
+
+    // shrink the size to what actually fits in `buf`, in case /dev/fb0-info and the mapping disagree
+    pub fn clip_to_buf(&mut self) {
+        let Some(len) = self.buf.as_ref().map(|buf| buf.len()) else {
+            return;
+        };
+        let bytes = self.format.bytes_per_pixel();
+
+        let (width, height) = (self.width, self.height);
+        self.width = self.width.min(self.pitch / bytes);
+        if self.height > 0 && self.pitch > 0 {
+            // the last row doesn't need the padding after it
+            let row = self.width * bytes;
+            self.height = self.height.min((len + self.pitch - row) / self.pitch);
+        }
+
+        if (width, height) != (self.width, self.height) {
+            eprintln!(
+                "fb: {width}x{height} doesn't fit in the {len} byte mapping, using {}x{}",
+                self.width, self.height
+            );
+        }
+    }
+}
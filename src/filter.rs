@@ -0,0 +1,102 @@
+// upscaling filters for the blitter, all pixels here are XRGB8888 like DG_ScreenBuffer
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    // blocky pixels
+    #[default]
+    Nearest,
+    // Scale2x/EPX edge smoothing of the 320x200 image, then nearest to the viewport
+    Scale2x,
+    // linear interpolation of the 320x200 image
+    Bilinear,
+    // nearest with every other half of a 320x200 line darkened
+    Scanlines,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "nearest" => Self::Nearest,
+            "scale2x" | "epx" => Self::Scale2x,
+            "bilinear" | "linear" => Self::Bilinear,
+            "scanlines" | "crt" => Self::Scanlines,
+            _ => return None,
+        })
+    }
+}
+
+//
+
+// i_video.c draws doom's 320x200 frame into DG_ScreenBuffer scaled 2x,
+// the smoothing filters want the original pixels
+pub fn downsample_2x(src: &[u32], src_width: usize, dst: &mut [u32]) {
+    let dst_width = src_width / 2;
+    for (dst_row, src_row) in dst
+        .chunks_exact_mut(dst_width)
+        .zip(src.chunks_exact(src_width * 2))
+    {
+        for (dst, src) in dst_row.iter_mut().zip(src_row.iter().step_by(2)) {
+            *dst = *src;
+        }
+    }
+}
+
+// Scale2x (aka. AdvMAME2x/EPX), `dst` is `2 * width` by `2 * height`
+pub fn scale2x(src: &[u32], width: usize, height: usize, dst: &mut [u32]) {
+    let at = |x: usize, y: usize| src[x + y * width];
+
+    for y in 0..height {
+        for x in 0..width {
+            let p = at(x, y);
+            let a = at(x, y.saturating_sub(1));
+            let b = at((x + 1).min(width - 1), y);
+            let c = at(x.saturating_sub(1), y);
+            let d = at(x, (y + 1).min(height - 1));
+
+            let (mut e0, mut e1, mut e2, mut e3) = (p, p, p, p);
+            if c == a && c != d && a != b {
+                e0 = a;
+            }
+            if a == b && a != c && b != d {
+                e1 = b;
+            }
+            if d == c && d != b && c != a {
+                e2 = c;
+            }
+            if b == d && b != a && d != c {
+                e3 = d;
+            }
+
+            let top = 2 * x + 4 * y * width;
+            dst[top] = e0;
+            dst[top + 1] = e1;
+            dst[top + 2 * width] = e2;
+            dst[top + 2 * width + 1] = e3;
+        }
+    }
+}
+
+// blend `a` and `b` per channel, `f` is the weight of `b` out of 256
+pub fn lerp(a: u32, b: u32, f: u32) -> u32 {
+    let rb = (((a & 0xff00ff) * (256 - f) + (b & 0xff00ff) * f) >> 8) & 0xff00ff;
+    let g = (((a & 0x00ff00) * (256 - f) + (b & 0x00ff00) * f) >> 8) & 0x00ff00;
+    rb | g
+}
+
+// scanlines are dimmed to 75%
+pub fn darken(px: u32) -> u32 {
+    ((px >> 2) & 0x3f3f3f) * 3
+}
+
+// bilinear sample positions: the 2 source pixels and the weight of the second one
+pub fn lerp_map(dst_len: usize, src_len: usize) -> impl Iterator<Item = (usize, usize, u32)> {
+    (0..dst_len).map(move |d| {
+        // source position of the destination pixel center, in 1/256ths
+        let pos = ((2 * d + 1) * src_len * 256 / (2 * dst_len)).saturating_sub(128);
+        let first = (pos / 256).min(src_len - 1);
+        let second = (first + 1).min(src_len - 1);
+        (first, second, (pos % 256) as u32)
+    })
+}
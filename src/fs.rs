@@ -0,0 +1,21 @@
+use alloc::{format, string::String, vec};
+use libstd::{fs::OpenOptions, io::Read};
+
+//
+
+// the whole file at `path` as utf-8, the error says which step failed on which file
+pub fn read_text(path: &str) -> Result<String, String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .map_err(|err| format!("failed to open {path}: {err}"))?;
+    let len = file
+        .metadata()
+        .map_err(|err| format!("failed to read {path}: {err}"))?
+        .len;
+
+    let mut buf = vec![0u8; len];
+    file.read_exact(&mut buf)
+        .map_err(|err| format!("failed to read {path}: {err}"))?;
+    String::from_utf8(buf).map_err(|_| format!("{path} is not utf-8"))
+}
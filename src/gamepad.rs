@@ -0,0 +1,154 @@
+use core::mem;
+
+use alloc::{string::String, vec::Vec};
+use libstd::sync::Mutex;
+
+use crate::{
+    device::{self, Reader},
+    input::{Source, KEY_STATE},
+    keymap::{BUTTON_COUNT, KEYMAP},
+};
+
+//
+
+// the latest controller state, posted to doom as ev_joystick every tic
+pub static GAMEPAD: Mutex<Gamepad> = Mutex::new(Gamepad::new());
+
+// hyperion's controller event stream, one json state per line
+pub const DEFAULT_DEVICE: &str = "/dev/gamepad";
+
+// a stick within this fraction of the center counts as centered
+pub const DEFAULT_DEAD_ZONE: f32 = 1.0 / 3.0;
+
+//
+
+#[derive(Debug)]
+pub struct Gamepad {
+    connected: bool,
+    // the centered state still has to be posted once after a disconnect,
+    // doom keeps moving with the last one it got otherwise
+    released: bool,
+    dead_zone: i32,
+
+    // the buttons that are not mapped to keys, bit n is button n
+    buttons: u32,
+    turn: i32,
+    forward: i32,
+    strafe: i32,
+}
+
+// the whole controller state, sticks are -32768..=32767 with y pointing down
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct GamepadEventSer {
+    #[serde(default)]
+    left_x: i16,
+    #[serde(default)]
+    left_y: i16,
+    #[serde(default)]
+    right_x: i16,
+    #[serde(default)]
+    buttons: u32,
+}
+
+impl Gamepad {
+    pub const fn new() -> Self {
+        Self {
+            connected: false,
+            released: false,
+            dead_zone: 0,
+            buttons: 0,
+            turn: 0,
+            forward: 0,
+            strafe: 0,
+        }
+    }
+
+    fn axis(&self, value: i16) -> i32 {
+        let value = value as i32;
+        if value.abs() < self.dead_zone {
+            0
+        } else {
+            value
+        }
+    }
+
+    // buttons, turn, forward (negative is forward) and strafe, if a controller is connected
+    pub fn state(&mut self) -> Option<(i32, i32, i32, i32)> {
+        if !self.connected && !mem::take(&mut self.released) {
+            return None;
+        }
+        Some((self.buttons as i32, self.turn, self.forward, self.strafe))
+    }
+}
+
+// read controller events from `device` in the background,
+// a missing default device just means there is no controller
+//
+// the left stick moves and strafes, the right stick turns,
+// buttons with a `button <n>` line in the keymap are sent as keys
+pub fn spawn_reader(device: Option<String>, dead_zone: f32) {
+    GAMEPAD.lock().dead_zone = (dead_zone.clamp(0.0, 1.0) * 32768.0) as i32;
+
+    let optional = device.is_none();
+    let device = device.unwrap_or_else(|| DEFAULT_DEVICE.into());
+    device::spawn_reader("gamepad", Some(device), optional, GamepadReader::default());
+}
+
+#[derive(Default)]
+struct GamepadReader {
+    pending: Vec<u8>,
+}
+
+impl Reader for GamepadReader {
+    fn read(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        device::lines("gamepad", &mut self.pending, |line| {
+            if let Ok(ev) = serde_json::from_str::<GamepadEventSer>(line) {
+                update(ev);
+            }
+        });
+    }
+
+    fn closed(&mut self) {
+        self.pending.clear();
+        KEY_STATE.lock().release_all(Source::Gamepad);
+
+        let mut gamepad = GAMEPAD.lock();
+        gamepad.connected = false;
+        gamepad.released = true;
+        gamepad.buttons = 0;
+        gamepad.turn = 0;
+        gamepad.forward = 0;
+        gamepad.strafe = 0;
+    }
+}
+
+fn update(ev: GamepadEventSer) {
+    // buttons mapped to keys become key presses, the rest stay joystick buttons
+    let mut buttons = ev.buttons;
+    {
+        let keymap = KEYMAP.lock();
+        let mut keys = KEY_STATE.lock();
+        for button in 0..BUTTON_COUNT {
+            let bit = 1u32 << button;
+            let Some(key) = keymap.button(button) else {
+                continue;
+            };
+            buttons &= !bit;
+
+            // every event has the whole state, so unchanged buttons are no-ops
+            if ev.buttons & bit != 0 {
+                keys.press(Source::Gamepad, button, key, 0);
+            } else {
+                keys.release(Source::Gamepad, button);
+            }
+        }
+    }
+
+    let mut gamepad = GAMEPAD.lock();
+    gamepad.connected = true;
+    gamepad.buttons = buttons;
+    gamepad.turn = gamepad.axis(ev.right_x);
+    gamepad.forward = gamepad.axis(ev.left_y);
+    gamepad.strafe = gamepad.axis(ev.left_x);
+}
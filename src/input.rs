@@ -0,0 +1,373 @@
+use core::mem;
+
+use alloc::{string::String, vec::Vec};
+use libstd::{eprintln, sync::Mutex};
+
+use crate::{
+    device::{self, Reader},
+    keymap::{to_ascii, BUTTON_COUNT, KEYMAP, KEY_NUMLOCK},
+    Ev, KEYS,
+};
+
+//
+
+// every key that doom has been told is down, all key events go through here
+pub static KEY_STATE: Mutex<KeyState> = Mutex::new(KeyState::new());
+
+// binary frames are a length byte followed by that many bytes, the first one is the event kind
+//
+// a key event is `KIND_KEY, state (0 is pressed), keycode, unicode (u32 le, 0 if none)`
+// optionally followed by the scancode, a text event is `KIND_TEXT, unicode (u32 le)`,
+// frames may be longer than that, new fields only ever get added to the end
+const KIND_KEY: u8 = 1;
+const KIND_TEXT: u8 = 2;
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Binary,
+    // `{"state":0,"keycode":40,"unicode":"w"}` or `{"text":"é"}` per line,
+    // what hyperion's terminal writes to stdin
+    Json,
+}
+
+impl InputFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "binary" => Some(Self::Binary),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InputEvent {
+    Key(KeyEvent),
+    // typed without a key of its own, like with a compose key
+    Text(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct KeyEvent {
+    pressed: bool,
+    keycode: u8,
+    // the PS/2 set 1 scancode, where the key physically is
+    scancode: Option<u8>,
+    unicode: Option<char>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct KeyboardEventSer {
+    #[serde(default)]
+    scancode: Option<u8>,
+    state: u8,
+    keycode: u8,
+    unicode: Option<char>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct TextEventSer {
+    text: char,
+}
+
+//
+
+// read keyboard events from `device` (stdin if none) in the background
+pub fn spawn_reader(device: Option<String>, format: InputFormat) {
+    device::spawn_reader("input", device, false, Keyboard::new(format));
+}
+
+// move every complete event out of `pending`, anything malformed is skipped
+fn decode(format: InputFormat, pending: &mut Vec<u8>, out: &mut Vec<InputEvent>) {
+    match format {
+        InputFormat::Binary => {
+            let mut at = 0;
+            while let Some(&len) = pending.get(at) {
+                let Some(frame) = pending.get(at + 1..at + 1 + len as usize) else {
+                    break;
+                };
+                at += 1 + len as usize;
+
+                match *frame {
+                    [KIND_KEY, state, keycode, u0, u1, u2, u3, ref rest @ ..] => {
+                        out.push(InputEvent::Key(KeyEvent {
+                            pressed: state == 0,
+                            keycode,
+                            scancode: rest.first().copied().filter(|&s| s != 0),
+                            unicode: char::from_u32(u32::from_le_bytes([u0, u1, u2, u3]))
+                                .filter(|&c| c != '\0'),
+                        }))
+                    }
+                    [KIND_TEXT, u0, u1, u2, u3, ..] => {
+                        if let Some(c) = char::from_u32(u32::from_le_bytes([u0, u1, u2, u3])) {
+                            out.push(InputEvent::Text(c));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            pending.drain(..at);
+        }
+        InputFormat::Json => device::lines("input", pending, |line| {
+            if let Ok(ev) = serde_json::from_str::<KeyboardEventSer>(line) {
+                out.push(InputEvent::Key(KeyEvent {
+                    pressed: ev.state == 0,
+                    keycode: ev.keycode,
+                    scancode: ev.scancode,
+                    unicode: ev.unicode,
+                }));
+            } else if let Ok(ev) = serde_json::from_str::<TextEventSer>(line) {
+                out.push(InputEvent::Text(ev.text));
+            } else {
+                eprintln!("input: invalid event {line:?}");
+            }
+        }),
+    }
+}
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    // by hyperion keycode
+    Keyboard,
+    // by button number
+    Gamepad,
+}
+
+#[derive(Debug)]
+pub struct KeyState {
+    // the doom key each keycode and gamepad button is holding down
+    keyboard: [Option<u8>; 256],
+    gamepad: [Option<u8>; BUTTON_COUNT],
+    // how many of those hold each doom key, doom only sees the first press and the last release
+    count: [u8; 256],
+}
+
+impl KeyState {
+    pub const fn new() -> Self {
+        Self {
+            keyboard: [None; 256],
+            gamepad: [None; BUTTON_COUNT],
+            count: [0; 256],
+        }
+    }
+
+    pub fn is_held(&self, key: u8) -> bool {
+        self.count[key as usize] != 0
+    }
+
+    fn slot(&mut self, source: Source, code: usize) -> &mut Option<u8> {
+        match source {
+            Source::Keyboard => &mut self.keyboard[code],
+            Source::Gamepad => &mut self.gamepad[code],
+        }
+    }
+
+    // false if `code` was already holding `key`, auto-repeat doesn't press it again
+    pub fn press(&mut self, source: Source, code: usize, key: u8, typed: u8) -> bool {
+        match *self.slot(source, code) {
+            Some(held) if held == key => return false,
+            // the mapping changed while it was down, like the keypad with num lock
+            Some(_) => self.release(source, code),
+            None => {}
+        }
+
+        *self.slot(source, code) = Some(key);
+        self.count[key as usize] += 1;
+        if self.count[key as usize] == 1 {
+            KEYS.push(Ev {
+                key,
+                pressed: 1,
+                typed,
+                text: false,
+            });
+        }
+        true
+    }
+
+    pub fn release(&mut self, source: Source, code: usize) {
+        let Some(key) = self.slot(source, code).take() else {
+            return;
+        };
+
+        self.count[key as usize] -= 1;
+        if self.count[key as usize] == 0 {
+            KEYS.push(Ev {
+                key,
+                pressed: 0,
+                typed: 0,
+                text: false,
+            });
+        }
+    }
+
+    // a press and release of the key for `typed`, unless that key is held down already,
+    // doom only gets them in its menus and text fields
+    pub fn tap(&mut self, key: u8, typed: u8) {
+        KEYS.push(Ev {
+            key,
+            pressed: 1,
+            typed,
+            text: true,
+        });
+        if self.count[key as usize] == 0 {
+            KEYS.push(Ev {
+                key,
+                pressed: 0,
+                typed: 0,
+                text: true,
+            });
+        }
+    }
+
+    // for when the source is gone and nobody is going to send the releases
+    pub fn release_all(&mut self, source: Source) {
+        let codes = match source {
+            Source::Keyboard => self.keyboard.len(),
+            Source::Gamepad => self.gamepad.len(),
+        };
+        for code in 0..codes {
+            self.release(source, code);
+        }
+    }
+}
+
+// the hyperion keyboard state that the keymap lookup depends on
+struct Keyboard {
+    format: InputFormat,
+    // the start of an event that hasn't been read completely yet
+    pending: Vec<u8>,
+    events: Vec<InputEvent>,
+    // num lock starts off, so the keypad moves like in chocolate doom
+    numlock: bool,
+}
+
+impl Reader for Keyboard {
+    fn read(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+
+        let mut events = mem::take(&mut self.events);
+        decode(self.format, &mut self.pending, &mut events);
+        for ev in events.drain(..) {
+            self.handle(ev);
+        }
+        self.events = events;
+    }
+
+    fn closed(&mut self) {
+        KEY_STATE.lock().release_all(Source::Keyboard);
+        self.pending.clear();
+    }
+}
+
+impl Keyboard {
+    fn new(format: InputFormat) -> Self {
+        Self {
+            format,
+            pending: Vec::new(),
+            events: Vec::new(),
+            numlock: false,
+        }
+    }
+
+    fn handle(&mut self, ev: InputEvent) {
+        let ev = match ev {
+            InputEvent::Key(ev) => ev,
+            InputEvent::Text(c) => {
+                if let Some(typed) = to_ascii(c) {
+                    KEY_STATE.lock().tap(typed.to_ascii_lowercase(), typed);
+                }
+                return;
+            }
+        };
+
+        let code = ev.keycode as usize;
+        if !ev.pressed {
+            KEY_STATE.lock().release(Source::Keyboard, code);
+            return;
+        }
+
+        let Some(key) = KEYMAP
+            .lock()
+            .get(ev.keycode, ev.scancode, ev.unicode, self.numlock)
+        else {
+            return;
+        };
+
+        // game actions go by position, text goes by what the layout says the key types
+        let typed = ev.unicode.and_then(to_ascii).unwrap_or(0);
+        let pressed = KEY_STATE.lock().press(Source::Keyboard, code, key, typed);
+        if pressed && key == KEY_NUMLOCK {
+            self.numlock = !self.numlock;
+        }
+    }
+}
+
+fn _decode_assert(format: InputFormat, input: &[u8], expected: &[InputEvent], left: &[u8]) {
+    let mut pending = input.to_vec();
+    let mut out = Vec::new();
+    decode(format, &mut pending, &mut out);
+    assert_eq!(out, expected, "decode({format:?}, {input:?})");
+    assert_eq!(pending, left, "decode({format:?}, {input:?}) left");
+}
+
+pub fn _decode_test() {
+    let key = |pressed, keycode, scancode, unicode| {
+        InputEvent::Key(KeyEvent {
+            pressed,
+            keycode,
+            scancode,
+            unicode,
+        })
+    };
+
+    // without and with the scancode, a longer frame, a text event
+    // and then a frame that isn't all there yet
+    _decode_assert(
+        InputFormat::Binary,
+        &[
+            7, KIND_KEY, 0, 40, b'w', 0, 0, 0, //
+            8, KIND_KEY, 1, 40, b'w', 0, 0, 0, 0x11, //
+            9, KIND_KEY, 0, 12, 0, 0, 0, 0, 0, 0xff, //
+            5, KIND_TEXT, 0xe9, 0, 0, 0, //
+            7, KIND_KEY, 0, 40,
+        ],
+        &[
+            key(true, 40, None, Some('w')),
+            key(false, 40, Some(0x11), Some('w')),
+            key(true, 12, None, None),
+            InputEvent::Text('é'),
+        ],
+        &[7, KIND_KEY, 0, 40],
+    );
+    // unknown kinds, short frames and invalid chars are skipped
+    _decode_assert(
+        InputFormat::Binary,
+        &[
+            2, 9, 0, //
+            3, KIND_KEY, 0, 40, //
+            5, KIND_TEXT, 0, 0xd8, 0, 0, //
+            0,
+        ],
+        &[],
+        &[],
+    );
+
+    _decode_assert(
+        InputFormat::Json,
+        b"{\"state\":0,\"keycode\":40,\"unicode\":\"w\"}\n\n\
+          {\"state\":1,\"keycode\":40,\"unicode\":null,\"scancode\":17}\n\
+          {\"text\":\"\xc3\xa9\"}\n\
+          {\"state\":0,",
+        &[
+            key(true, 40, None, Some('w')),
+            key(false, 40, Some(17), None),
+            InputEvent::Text('é'),
+        ],
+        b"{\"state\":0,",
+    );
+    _decode_assert(InputFormat::Json, b"{\"keycode\":40}\nnot json\n", &[], &[]);
+}
@@ -0,0 +1,492 @@
+use alloc::{format, string::String, vec::Vec};
+use libstd::{eprintln, println, sync::Mutex};
+
+use crate::fs;
+
+//
+
+// hyperion keycode -> doomkeys.h key, used by the stdin input thread
+pub static KEYMAP: Mutex<Keymap> = Mutex::new(Keymap::new());
+
+// `keymap.cfg` sits next to `default.cfg`, doom keeps that in the working directory
+pub const DEFAULT_PATH: &str = "keymap.cfg";
+
+// the names from doomkeys.h
+const NAMES: &[(&str, u8)] = &[
+    ("KEY_RIGHTARROW", 0xae),
+    ("KEY_LEFTARROW", 0xac),
+    ("KEY_UPARROW", 0xad),
+    ("KEY_DOWNARROW", 0xaf),
+    ("KEY_STRAFE_L", 0xa0),
+    ("KEY_STRAFE_R", 0xa1),
+    ("KEY_USE", 0xa2),
+    ("KEY_FIRE", 0xa3),
+    ("KEY_ESCAPE", 27),
+    ("KEY_ENTER", 13),
+    ("KEY_TAB", 9),
+    ("KEY_F1", 0x80 + 0x3b),
+    ("KEY_F2", 0x80 + 0x3c),
+    ("KEY_F3", 0x80 + 0x3d),
+    ("KEY_F4", 0x80 + 0x3e),
+    ("KEY_F5", 0x80 + 0x3f),
+    ("KEY_F6", 0x80 + 0x40),
+    ("KEY_F7", 0x80 + 0x41),
+    ("KEY_F8", 0x80 + 0x42),
+    ("KEY_F9", 0x80 + 0x43),
+    ("KEY_F10", 0x80 + 0x44),
+    ("KEY_F11", 0x80 + 0x57),
+    ("KEY_F12", 0x80 + 0x58),
+    ("KEY_BACKSPACE", 0x7f),
+    ("KEY_PAUSE", 0xff),
+    ("KEY_EQUALS", 0x3d),
+    ("KEY_MINUS", 0x2d),
+    ("KEY_RSHIFT", 0x80 + 0x36),
+    ("KEY_RCTRL", 0x80 + 0x1d),
+    ("KEY_RALT", 0x80 + 0x38),
+    ("KEY_LALT", 0x80 + 0x38),
+    ("KEY_CAPSLOCK", 0x80 + 0x3a),
+    ("KEY_NUMLOCK", 0x80 + 0x45),
+    ("KEY_SCRLCK", 0x80 + 0x46),
+    ("KEY_PRTSCR", 0x80 + 0x59),
+    ("KEY_HOME", 0x80 + 0x47),
+    ("KEY_END", 0x80 + 0x4f),
+    ("KEY_PGUP", 0x80 + 0x49),
+    ("KEY_PGDN", 0x80 + 0x51),
+    ("KEY_INS", 0x80 + 0x52),
+    ("KEY_DEL", 0x80 + 0x53),
+    ("KEYP_0", 0),
+    ("KEYP_1", 0x80 + 0x4f),
+    ("KEYP_2", 0xaf),
+    ("KEYP_3", 0x80 + 0x51),
+    ("KEYP_4", 0xac),
+    ("KEYP_5", b'5'),
+    ("KEYP_6", 0xae),
+    ("KEYP_7", 0x80 + 0x47),
+    ("KEYP_8", 0xad),
+    ("KEYP_9", 0x80 + 0x49),
+    ("KEYP_DIVIDE", b'/'),
+    ("KEYP_PLUS", b'+'),
+    ("KEYP_MINUS", b'-'),
+    ("KEYP_MULTIPLY", b'*'),
+    ("KEYP_PERIOD", 0),
+    ("KEYP_EQUALS", 0x3d),
+    ("KEYP_ENTER", 13),
+];
+
+// hyperion keycodes number the keys of a full size US keyboard row by row, left to right:
+//
+//   0 escape, 1-12 f1-f12, 13 print screen, 14 scroll lock, 15 pause
+//   16-30 the number row up to backspace, 31 insert, 32 home, 33 pg up,
+//     34 num lock, 35-37 keypad / * -
+//   38 tab, 39-52 q to \, 53 delete, 54 end, 55 pg down, 56-58 keypad 7-9, 59 keypad +
+//   60 caps lock, 61-72 a to enter, 73-75 keypad 4-6
+//   76 lshift, 77-87 z to rshift, 88 up, 89-91 keypad 1-3, 92 keypad enter
+//   93 lctrl, 95 lalt, 96 space, 97 ralt, 100 rctrl, 101 left, 102 down, 103 right,
+//     104 keypad 0, 105 keypad period
+//
+// keys that are not in here are sent as their ascii character, if they have one,
+// 0 means the key does nothing
+const DEFAULT: &[(u8, u8)] = &[
+    // (40, 0xad), // W - up
+    // (61, 0xa0), // A - strafe left
+    // (62, 0xaf), // S - down
+    // (63, 0xa1), // D - strafe right
+    (103, 0xae),      // right
+    (101, 0xac),      // left
+    (88, 0xad),       // up
+    (102, 0xaf),      // down
+    (84, 0xa0),       // comma - strafe left
+    (85, 0xa1),       // period - strafe right
+    (96, 0xa2),       // space - use
+    (0, 27),          // escape
+    (72, 13),         // enter
+    (38, 9),          // tab
+    (1, 0x80 + 0x3b), // f1-12
+    (2, 0x80 + 0x3c),
+    (3, 0x80 + 0x3d),
+    (4, 0x80 + 0x3e),
+    (5, 0x80 + 0x3f),
+    (6, 0x80 + 0x40),
+    (7, 0x80 + 0x41),
+    (8, 0x80 + 0x42),
+    (9, 0x80 + 0x43),
+    (10, 0x80 + 0x44),
+    (11, 0x80 + 0x57),
+    (12, 0x80 + 0x58),
+    (17, b'0'),
+    (18, b'1'),
+    (19, b'2'),
+    (20, b'3'),
+    (21, b'4'),
+    (22, b'5'),
+    (23, b'6'),
+    (24, b'7'),
+    (25, b'8'),
+    (26, b'9'),
+    (30, 0x7f),        // backspace
+    (15, 0xff),        // pause
+    (29, 0x3d),        // equals
+    (28, 0x2d),        // minus
+    (76, 0x80 + 0x36), // lshift
+    (87, 0x80 + 0x36), // rshift
+    (93, 0xa3),        // lctrl - fire
+    (100, 0xa3),       // rctrl - fire
+    (95, 0x80 + 0x38), // lalt
+    (97, 0x80 + 0x38), // ralt
+    (60, 0x80 + 0x3a), // capslock
+    (34, 0x80 + 0x45), // numlock
+    // (14, 0x80 + 0x46), // scrlock
+    // (13, 0x80 + 0x59), // print screen
+    (32, 0x80 + 0x47), // home
+    (54, 0x80 + 0x4f), // end
+    (33, 0x80 + 0x49), // pg up
+    (55, 0x80 + 0x51), // pg down
+    // (?, 0x80 + 0x52), // insert
+    (53, 0x80 + 0x53), // delete
+    // the keypad with num lock off, same as chocolate doom's KEYP_ keys
+    (104, 0),          // 0
+    (89, 0x80 + 0x4f), // 1, end
+    (90, 0xaf),        // 2, down
+    (91, 0x80 + 0x51), // 3, pg down
+    (73, 0xac),        // 4, left
+    (74, b'5'),        // 5
+    (75, 0xae),        // 6, right
+    (56, 0x80 + 0x47), // 7, home
+    (57, 0xad),        // 8, up
+    (58, 0x80 + 0x49), // 9, pg up
+    (35, b'/'),        // divide
+    (59, b'+'),        // plus
+    (37, b'-'),        // minus
+    (36, b'*'),        // mult
+    (105, 0),          // period
+    (92, 13),          // enter
+];
+
+// the keypad keys that type a character instead while num lock is on
+const NUMLOCK: &[(u8, u8)] = &[
+    (104, b'0'),
+    (89, b'1'),
+    (90, b'2'),
+    (91, b'3'),
+    (73, b'4'),
+    (74, b'5'),
+    (75, b'6'),
+    (56, b'7'),
+    (57, b'8'),
+    (58, b'9'),
+    (105, b'.'),
+];
+
+// the printable keys by where they are on a US keyboard, keyed by PS/2 set 1 scancode,
+// so the bindings stay on the same physical keys with any layout
+const POSITIONS: &[(u8, u8)] = &[
+    (0x02, b'1'),
+    (0x03, b'2'),
+    (0x04, b'3'),
+    (0x05, b'4'),
+    (0x06, b'5'),
+    (0x07, b'6'),
+    (0x08, b'7'),
+    (0x09, b'8'),
+    (0x0a, b'9'),
+    (0x0b, b'0'),
+    (0x0c, b'-'),
+    (0x0d, b'='),
+    (0x10, b'q'),
+    (0x11, b'w'),
+    (0x12, b'e'),
+    (0x13, b'r'),
+    (0x14, b't'),
+    (0x15, b'y'),
+    (0x16, b'u'),
+    (0x17, b'i'),
+    (0x18, b'o'),
+    (0x19, b'p'),
+    (0x1a, b'['),
+    (0x1b, b']'),
+    (0x1e, b'a'),
+    (0x1f, b's'),
+    (0x20, b'd'),
+    (0x21, b'f'),
+    (0x22, b'g'),
+    (0x23, b'h'),
+    (0x24, b'j'),
+    (0x25, b'k'),
+    (0x26, b'l'),
+    (0x27, b';'),
+    (0x28, b'\''),
+    (0x29, b'`'),
+    (0x2b, b'\\'),
+    (0x2c, b'z'),
+    (0x2d, b'x'),
+    (0x2e, b'c'),
+    (0x2f, b'v'),
+    (0x30, b'b'),
+    (0x31, b'n'),
+    (0x32, b'm'),
+    (0x33, b','),
+    (0x34, b'.'),
+    (0x35, b'/'),
+    (0x39, b' '),
+];
+
+// gamepad buttons that are sent as keys instead of joystick buttons
+const BUTTONS: &[(u8, u8)] = &[
+    (6, 9),  // back - tab
+    (7, 27), // start - escape
+];
+
+// U+00A0..=U+00FF as the closest ascii character, 0 if there isn't one,
+// doom's font has no accents and one key has to type one character
+const LATIN1: &[u8; 96] = b" !cL\0Y|S\"Ca<--R-\
+                            o+23'uP.,1o>\0\0\0?\
+                            AAAAAAACEEEEIIII\
+                            DNOOOOOxOUUUUYTs\
+                            aaaaaaaceeeeiiii\
+                            dnooooo/ouuuuyty";
+
+// doom's num lock key, any keycode mapped to it toggles the keypad between the two tables
+pub const KEY_NUMLOCK: u8 = 0x80 + 0x45;
+
+//
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    keys: [Option<u8>; 256],
+    numlock: [Option<u8>; 256],
+    positions: [Option<u8>; 256],
+    buttons: [Option<u8>; BUTTON_COUNT],
+}
+
+// gamepad buttons are a bitmask in ev_joystick
+pub const BUTTON_COUNT: usize = 32;
+
+impl Keymap {
+    pub const fn new() -> Self {
+        Self {
+            keys: table(DEFAULT),
+            numlock: table(NUMLOCK),
+            positions: table(POSITIONS),
+            buttons: table(BUTTONS),
+        }
+    }
+
+    // the doom key for a gamepad button, if it isn't left to doom's joystick button bindings
+    pub fn button(&self, button: usize) -> Option<u8> {
+        self.buttons[button]
+    }
+
+    // the doom key for a hyperion keycode, keys without a keycode mapping
+    // go by their scancode and only then by the character they type
+    pub fn get(
+        &self,
+        keycode: u8,
+        scancode: Option<u8>,
+        unicode: Option<char>,
+        numlock: bool,
+    ) -> Option<u8> {
+        let numlock = self.numlock[keycode as usize].filter(|_| numlock);
+        let position = scancode.and_then(|s| self.positions[s as usize]);
+        match numlock.or(self.keys[keycode as usize]).or(position) {
+            Some(0) => None,
+            Some(key) => Some(key),
+            None => unicode.and_then(|c| match c.is_ascii_control() {
+                true => Some(c as u8),
+                false => to_ascii(c).map(|c| c.to_ascii_lowercase()),
+            }),
+        }
+    }
+
+    // the default keymap with the file at `path` on top of it, every invalid line is reported
+    // and skipped; a missing file is only an error if it was asked for explicitly
+    pub fn load(path: Option<&str>) -> Self {
+        let mut keymap = Self::new();
+        let text = match fs::read_text(path.unwrap_or(DEFAULT_PATH)) {
+            Ok(text) => text,
+            Err(err) if path.is_some() => {
+                eprintln!("keymap: {err}, using the default keymap");
+                return keymap;
+            }
+            Err(_) => return keymap,
+        };
+        let path = path.unwrap_or(DEFAULT_PATH);
+
+        let errors = keymap.apply(&text);
+        for err in &errors {
+            eprintln!("keymap: {path}:{err}");
+        }
+        println!(
+            "keymap: loaded {path}{}",
+            if errors.is_empty() {
+                String::new()
+            } else {
+                format!(" with {} errors", errors.len())
+            }
+        );
+
+        keymap
+    }
+
+    // one `<hyperion keycode> <doom key>` or `button <gamepad button> <doom key>` per line,
+    // `#` starts a comment
+    //
+    // the doom key is a doomkeys.h name (`KEY_UPARROW`), a single character (`w`),
+    // a number (`0xad`) or `none` to ignore the keycode,
+    // a remapped keypad key ignores num lock and a button mapped to `none` is a joystick button again
+    pub fn apply(&mut self, text: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut seen = [false; 256];
+        let mut seen_buttons = [false; BUTTON_COUNT];
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let n = i + 1;
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            let (button, code, key) = match words[..] {
+                ["button", code, key] => (true, code, key),
+                ["button", ..] => {
+                    errors.push(format!(
+                        "{n}: expected `button <n> <doom key>`, got {line:?}"
+                    ));
+                    continue;
+                }
+                [code, key] => (false, code, key),
+                _ => {
+                    errors.push(format!(
+                        "{n}: expected `<keycode> <doom key>` or `button <n> <doom key>`, got {line:?}"
+                    ));
+                    continue;
+                }
+            };
+
+            let (what, max) = if button {
+                ("button", BUTTON_COUNT - 1)
+            } else {
+                ("keycode", 0xff)
+            };
+            let Some(code) = parse_number(code).filter(|&k| k as usize <= max) else {
+                errors.push(format!("{n}: invalid {what} {code:?}, expected 0-{max}"));
+                continue;
+            };
+            let code = code as usize;
+
+            let key = if key == "none" {
+                0
+            } else {
+                match parse_key(key) {
+                    Some(key) => key,
+                    None => {
+                        errors.push(format!(
+                            "{n}: invalid doom key {key:?}, expected a doomkeys.h name, a character or a number 1-255"
+                        ));
+                        continue;
+                    }
+                }
+            };
+
+            let seen = if button {
+                &mut seen_buttons[code]
+            } else {
+                &mut seen[code]
+            };
+            if *seen {
+                errors.push(format!(
+                    "{n}: {what} {code} is mapped twice, the last one is used"
+                ));
+            }
+            *seen = true;
+
+            if button {
+                self.buttons[code] = Some(key).filter(|&key| key != 0);
+            } else {
+                self.keys[code] = Some(key);
+                self.numlock[code] = None;
+            }
+        }
+
+        errors
+    }
+}
+
+// the printable ascii character that `c` is typed as
+pub fn to_ascii(c: char) -> Option<u8> {
+    let c = match c as u32 {
+        0x20..=0x7e => c as u8,
+        n @ 0xa0..=0xff => LATIN1[n as usize - 0xa0],
+        _ => 0,
+    };
+    (c != 0).then_some(c)
+}
+
+const fn table<const N: usize>(pairs: &[(u8, u8)]) -> [Option<u8>; N] {
+    let mut keys = [None; N];
+    let mut i = 0;
+    while i < pairs.len() {
+        let (keycode, key) = pairs[i];
+        keys[keycode as usize] = Some(key);
+        i += 1;
+    }
+    keys
+}
+
+fn parse_number(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+// a doomkeys.h name, a single character or a number
+pub fn parse_key(s: &str) -> Option<u8> {
+    if let Some(&(_, key)) = NAMES.iter().find(|(name, _)| *name == s) {
+        return Some(key);
+    }
+
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return c.is_ascii_graphic().then_some(c.to_ascii_lowercase() as u8);
+    }
+
+    parse_number(s)
+        .filter(|&key| (1..=0xff).contains(&key))
+        .map(|key| key as u8)
+}
+
+pub fn _keymap_test() {
+    let mut keymap = Keymap::new();
+    let errors =
+        keymap.apply("# overrides\n40 w # W\n12 KEY_F1\n13 0x80\n38 none\nbutton 6 none\n");
+    assert!(errors.is_empty(), "keymap errors: {errors:?}");
+    assert_eq!(keymap.get(40, None, None, false), Some(b'w'));
+    assert_eq!(keymap.get(12, None, None, false), Some(0x80 + 0x3b));
+    assert_eq!(keymap.get(13, None, None, false), Some(0x80));
+    assert_eq!(keymap.get(38, None, Some('\t'), false), None);
+    assert_eq!(keymap.button(6), None);
+    assert_eq!(keymap.button(7), Some(27));
+    // a remapped keypad key ignores num lock
+    keymap.apply("104 KEY_FIRE");
+    assert_eq!(keymap.get(104, None, None, true), Some(0xa3));
+
+    let mut keymap = Keymap::new();
+    let errors =
+        keymap.apply("12 KEY_NOPE\n300 a\nbutton 32 a\nbutton 1\n12\n12 a\n12 b\n13 0x100\n");
+    let expected = [
+        "1: invalid doom key",
+        "2: invalid keycode",
+        "3: invalid button",
+        "4: expected `button",
+        "5: expected `<keycode>",
+        "7: keycode 12 is mapped twice",
+        "8: invalid doom key",
+    ];
+    assert_eq!(errors.len(), expected.len(), "keymap errors: {errors:?}");
+    for (err, expected) in errors.iter().zip(expected) {
+        assert!(err.starts_with(expected), "{err:?}, expected: {expected:?}");
+    }
+    // the invalid lines are skipped, the duplicate is the last one
+    assert_eq!(keymap.get(12, None, None, false), Some(b'b'));
+}
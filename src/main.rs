@@ -3,30 +3,58 @@
 
 //
 
-use core::{ffi, ptr::NonNull, slice};
+use core::{ffi, ptr, slice};
 
-use alloc::{ffi::CString, string::String, vec::Vec};
+use alloc::{ffi::CString, string::String, vec, vec::Vec};
 use crossbeam::queue::SegQueue;
 use hyperion_color::Color;
 use libstd::{
     env::args,
-    eprintln,
-    fs::{File, OpenOptions},
-    io::{stdin, BufReader},
-    println,
+    eprintln, println,
     process::ExitCode,
     sync::Mutex,
-    sys::{map_file, nanosleep, rename, timestamp, unmap_file, yield_now},
-    thread::spawn,
+    sys::{nanosleep, rename, timestamp, yield_now},
 };
 
-use self::libc::{_atoi_test, _strlen_test, _strncasecmp_test, _strncmp_test};
+use self::{
+    backend::Backend,
+    blit::{Scaler, Viewport},
+    color::{_lut_parse_test, Grade, GradeParams, Lut},
+    fb::{Framebuffer, PixelFormat},
+    filter::Filter,
+    gamepad::GAMEPAD,
+    input::{_decode_test, InputFormat, KEY_STATE},
+    keymap::{_keymap_test, Keymap, KEYMAP},
+    libc::{_atoi_test, _strlen_test, _strncasecmp_test, _strncmp_test},
+    mouse::MOUSE,
+    options::{Options, OPTIONS},
+    perf::Perf,
+    record::Recorder,
+};
 
 //
 
 extern crate alloc;
 
+mod backend;
+mod blit;
+mod color;
+mod cp437;
+mod device;
+mod endoom;
+mod fb;
+mod filter;
+mod font;
+mod fs;
+mod gamepad;
+mod input;
+mod keymap;
 mod libc;
+mod mouse;
+mod options;
+mod perf;
+mod png;
+mod record;
 
 //
 
@@ -34,6 +62,16 @@ extern "C" {
     fn doomgeneric_Create(argc: ffi::c_int, argv: *const *const ffi::c_char) -> ffi::c_int;
 
     fn doomgeneric_Tick() -> ffi::c_int;
+
+    static mut DG_ScreenBuffer: *mut u32;
+    // in pixels
+    static mut DG_ScreenPitch: u32;
+
+    // doom's text entry states, typed text without a key of its own only goes to those,
+    // doom writes them every tic
+    static mut menuactive: ffi::c_int;
+    static mut saveStringEnter: ffi::c_int;
+    static mut chat_on: ffi::c_int;
 }
 
 //
@@ -49,6 +87,7 @@ static FB: Mutex<Framebuffer> = Mutex::new(Framebuffer {
     width: 0,
     height: 0,
     pitch: 0,
+    format: PixelFormat::XRGB8888,
     buf: None,
 });
 
@@ -56,205 +95,188 @@ static FB: Mutex<Framebuffer> = Mutex::new(Framebuffer {
 struct Ev {
     key: u8,
     pressed: i32,
+    // the character the key typed in the current layout, 0 if none
+    typed: u8,
+    // a text event without a key of its own, dropped outside of text entry
+    // so it doesn't fire the binding of the key it is sent as
+    text: bool,
 }
 
 static KEYS: SegQueue<Ev> = SegQueue::new();
-static FBO: Mutex<Option<(File, usize)>> = Mutex::new(None);
+static SCALER: Mutex<Option<Scaler>> = Mutex::new(None);
+static BACKEND: Mutex<Option<Backend>> = Mutex::new(None);
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+static PERF: Mutex<Perf> = Mutex::new(Perf::new());
+// the latest palette from I_SetPalette, until the scaler picks it up
+static PALETTE: Mutex<Option<[u32; 256]>> = Mutex::new(None);
+// set while DG_ScreenBuffer points into the framebuffer, to the buffer it pointed to before
+static ZERO_COPY: Mutex<Option<usize>> = Mutex::new(None);
 
 //
 
 #[no_mangle]
 extern "C" fn DG_Init() {}
 
+// doom's I_Quit and I_Error end up here, so this is where the screen gets restored,
+// a rust panic goes to libstd's panic handler instead and leaves doom's last frame on screen
 #[no_mangle]
 pub extern "C" fn exit(status: ffi::c_int) -> ! {
+    // the locks are held until the process is gone, so another thread
+    // can't draw over the restored screen or re-init the backend
     let mut fb = FB.lock();
-    let (w, h) = (fb.width, fb.height);
-    fb.fill(0, 0, w, h, Color::BLACK);
-    drop(fb);
+    let mut backend = BACKEND.lock();
+
+    if let Some(recorder) = RECORDER.lock().take() {
+        if let Err(err) = recorder.finish() {
+            eprintln!("failed to finish the video recording: {err}");
+        }
+    }
 
-    if let Some((fbo, fbo_mapped)) = FBO.lock().take() {
-        unmap_file(
-            fbo.as_desc(),
-            NonNull::new(fbo_mapped as *mut ()).unwrap(),
-            0,
-        )
-        .expect("failed to unmap the fb");
+    if let Some(backend) = backend.take() {
+        backend.close(&mut fb);
     }
 
     ExitCode::from_raw(status).exit_process()
 }
 
 fn lazy_init() {
-    if FBO.lock().is_some() {
+    if BACKEND.lock().is_some() {
         return;
     }
 
-    spawn(|| {
-        let mut stdin = stdin().lock();
-        let mut buf = String::new();
-        loop {
-            buf.clear();
-            if stdin.read_line(&mut buf).is_err() {
-                continue;
-            }
-            if buf.is_empty() {
-                eprintln!("EMPTY");
-                panic!();
-            }
-            eprintln!("{buf:?}");
-
-            #[derive(Debug, serde::Serialize, serde::Deserialize)]
-            struct KeyboardEventSer {
-                // pub scancode: u8,
-                state: u8,
-                keycode: u8,
-                unicode: Option<char>,
-            }
+    let opts = OPTIONS.lock().clone();
+    input::spawn_reader(
+        opts.input.clone(),
+        opts.input_format.unwrap_or(match opts.input {
+            Some(_) => InputFormat::Binary,
+            None => InputFormat::Json,
+        }),
+    );
+    if opts.mouse {
+        mouse::spawn_reader(
+            opts.mouse_device.clone(),
+            opts.mouse_sensitivity,
+            opts.mouse_invert,
+        );
+    }
+    if opts.gamepad {
+        gamepad::spawn_reader(opts.gamepad_device.clone(), opts.gamepad_dead_zone);
+    }
 
-            let Ok(ev) = serde_json::from_str::<KeyboardEventSer>(&buf.trim()) else {
-                continue;
-            };
-
-            let pressed = if ev.state == 0 { 1 } else { 0 };
-
-            // libstd::sys::log!("{:x}:", ev.keycode);
-
-            let key = match ev.keycode {
-                // 40 => 0xad, // W - up
-                // 61 => 0xa0, // A - strafe left
-                // 62 => 0xaf, // S - down
-                // 63 => 0xa1, // D - strafe right
-                103 => 0xae, // right
-                101 => 0xac, // left
-                88 => 0xad,  // up
-                102 => 0xaf, // down
-                84 => 0xa0,  // comma - strafe left
-                85 => 0xa1,  // period - strafe right
-                96 => 0xa2,  // space - use
-                // 93 => 0xa3,       // lctrl - fire
-                0 => 27,          // escape
-                72 => 13,         // enter
-                38 => 9,          // tab
-                1 => 0x80 + 0x3b, // f1-12
-                2 => 0x80 + 0x3c,
-                3 => 0x80 + 0x3d,
-                4 => 0x80 + 0x3e,
-                5 => 0x80 + 0x3f,
-                6 => 0x80 + 0x40,
-                7 => 0x80 + 0x41,
-                8 => 0x80 + 0x42,
-                9 => 0x80 + 0x43,
-                10 => 0x80 + 0x44,
-                11 => 0x80 + 0x57,
-                12 => 0x80 + 0x58,
-
-                17 => b'0',
-                18 => b'1',
-                19 => b'2',
-                20 => b'3',
-                21 => b'4',
-                22 => b'5',
-                23 => b'6',
-                24 => b'7',
-                25 => b'8',
-                26 => b'9',
-
-                30 => 0x7f, // backspace
-                34 => 0xff, // pause
-
-                29 => 0x3d, // equals
-                28 => 0x2d, // minus
-
-                76 | 87 => 0x80 + 0x36, // r/lshift
-                // 100 => 0x80 + 0x1d,     // rctrl
-                93 | 100 => 0xa3,       // r/lctrl
-                95 | 97 => 0x80 + 0x38, // r/l alt
-
-                60 => 0x80 + 0x3a, // capslock
-                // => 0x80+0x45, // numlock
-                // => 0x80+0x46, // scrlock
-                // => 0x80+0x59, // prtint screen
-
-                //
-                32 => 0x80 + 0x47, // home
-                54 => 0x80 + 0x4f, // end
-                33 => 0x80 + 0x49, // pg down
-                55 => 0x80 + 0x51, // pg up
-                // => 0x80+0x52, // insert
-                53 => 0x80 + 0x53, // delete
-
-                // TODO: keypad keys:
-                // => 0,           // 0
-                // => 0x80 + 0x4f, // 1, end
-                // => 0xaf,        // 2, down
-                // => 0x80 + 0x49, // 3, pg down
-                // => 0xac,        // 4, left
-                // => b'5',        // 5
-                // => 0xae,        // 6, right
-                // => 0x80 + 0x47, // 7, home
-                // => 0xad,        // 8, up
-                // => 0x80 + 0x51, // 9, pg up
-
-                // => b'/', // divide
-                // => b'+', // plus
-                // => b'-', // minus
-                // => b'*', // mult
-                // => 0, // period
-                // => 0x3d, // equals
-                // => 13, // enter
-                _ => {
-                    if let Some(c) = ev.unicode {
-                        if c.is_ascii() {
-                            c as _
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                }
-            };
+    let (backend, info) = match opts.headless.clone() {
+        Some(dir) => Backend::headless(
+            dir,
+            opts.headless_format,
+            opts.headless_every,
+            opts.headless_frames,
+            opts.headless_size,
+        ),
+        None => Backend::framebuffer(),
+    };
+
+    let mut fb = FB.lock();
+    *fb = info;
 
-            // libstd::sys::log!("{:x} -> {:x}", ev.keycode, key);
+    let viewport = fit_viewport(&mut fb, DOOMGENERIC_RESX, DOOMGENERIC_RESY);
+    drop(fb);
 
-            KEYS.push(Ev { key, pressed });
-        }
+    *SCALER.lock() = Some(Scaler::new(
+        viewport,
+        opts.filter,
+        opts.rotation,
+        DOOMGENERIC_RESX,
+        DOOMGENERIC_RESY,
+    ));
+
+    let lut = opts.lut.as_deref().and_then(|path| {
+        Lut::load(path)
+            .map_err(|err| eprintln!("failed to load the color LUT: {err}"))
+            .ok()
     });
+    if lut.is_some() || opts.grade != GradeParams::NEUTRAL {
+        if let Some(scaler) = SCALER.lock().as_mut() {
+            scaler.set_grade(Some(Grade::new(opts.grade, lut)));
+        }
+    }
 
-    let mut fb = FB.lock();
+    if let Some(path) = opts.record_video.as_deref() {
+        match Recorder::new(path, DOOMGENERIC_RESX, DOOMGENERIC_RESY) {
+            Ok(recorder) => *RECORDER.lock() = Some(recorder),
+            Err(err) => eprintln!("failed to open {path} for recording: {err}"),
+        }
+    }
 
-    let mut info = framebuffer_info();
+    *BACKEND.lock() = Some(backend);
+}
 
-    let fbo = OpenOptions::new()
-        .write(true)
-        .open("/dev/fb0")
-        .expect("failed to open /dev/fb0");
-    let meta = fbo.metadata().expect("failed to read fb file metadata");
+// where an image of `image_width`x`image_height` goes in the current framebuffer mode,
+// also clears the letterbox/pillarbox borders, only the viewport gets redrawn after this
+fn fit_viewport(fb: &mut Framebuffer, image_width: usize, image_height: usize) -> Viewport {
+    let opts = OPTIONS.lock();
 
-    let fbo_mapped = map_file(fbo.as_desc(), None, meta.len, 0).expect("failed to map the fb");
+    // fit the upright image into the framebuffer as the player sees it, then rotate the rect back
+    let rotation = opts.rotation;
+    let (width, height) = rotation.size(fb.width, fb.height);
+    let viewport = Viewport::choose(
+        width,
+        height,
+        image_width,
+        image_height,
+        opts.scale,
+        opts.aspect_correct,
+    );
+    let viewport = if rotation.swaps_axes() {
+        viewport.transpose()
+    } else {
+        viewport
+    };
+    println!(
+        "fb: {}x{} {:?}, viewport: {viewport:?}",
+        fb.width, fb.height, fb.format
+    );
 
-    let buf = unsafe { slice::from_raw_parts_mut(fbo_mapped.as_ptr() as *mut u8, meta.len) };
-    // let mut backbuf = Vec::leak(vec![0u8; buf.len()]);
-    // info.buf = backbuf;
-    info.buf = Some(buf);
+    let (w, h) = (fb.width, fb.height);
+    fb.fill(0, 0, w, h, Color::BLACK);
+    viewport
+}
 
-    *fb = info;
+#[no_mangle]
+extern "C" fn DG_SetPalette(colors: *const u32) {
+    let colors = unsafe { slice::from_raw_parts(colors, 256) };
+    *PALETTE.lock() = Some(colors.try_into().unwrap());
+}
 
-    // keep the file open
+#[no_mangle]
+extern "C" fn DG_DrawIndexed(screen: *const u8) -> ffi::c_int {
+    lazy_init();
+
+    // not while something reads DG_ScreenBuffer, see `update_zero_copy`
+    let usable = {
+        let opts = OPTIONS.lock();
+        opts.indexed && !opts.blit_bench
+    } && RECORDER.lock().is_none()
+        && ZERO_COPY.lock().is_none()
+        && SCALER
+            .lock()
+            .as_ref()
+            .is_some_and(|scaler| scaler.supports_indexed());
+    if !usable {
+        return 0;
+    }
 
-    let mut lock = FBO.lock();
-    *lock = Some((fbo, fbo_mapped.as_ptr() as usize));
+    let screen =
+        unsafe { slice::from_raw_parts(screen, DOOMGENERIC_RESX / 2 * DOOMGENERIC_RESY / 2) };
+    draw_frame(Frame::Indexed(screen));
+    1
 }
 
 #[no_mangle]
 extern "C" fn DG_DrawFrame() {
     lazy_init();
 
-    let mut fb = FB.lock();
-
-    extern "C" {
-        static DG_ScreenBuffer: *mut u32;
+    if ZERO_COPY.lock().is_some() {
+        draw_frame(Frame::Direct);
+        return;
     }
 
     let dg_buf = unsafe { DG_ScreenBuffer };
@@ -262,20 +284,190 @@ extern "C" fn DG_DrawFrame() {
         unsafe { slice::from_raw_parts(dg_buf as *const _, DOOMGENERIC_RESX * DOOMGENERIC_RESY) };
     // let dg_buf = unsafe { slice::from_raw_parts(dg_buf as *const u8, PITCH * DOOMGENERIC_RESY) };
 
-    for y in 0..DOOMGENERIC_RESY {
-        for x in 0..DOOMGENERIC_RESX {
-            let px = dg_buf[x + y * DOOMGENERIC_RESX];
-            let c = Color::from_u32(px);
-            fb.fill(x * 2, y * 2, 2, 2, Color::new(c.b, c.g, c.r));
+    draw_frame(Frame::Xrgb(dg_buf));
+}
+
+// what a frame is drawn from
+#[derive(Clone, Copy)]
+enum Frame<'a> {
+    // DG_ScreenBuffer
+    Xrgb(&'a [u32]),
+    // I_VideoBuffer, with the palette from `DG_SetPalette`
+    Indexed(&'a [u8]),
+    // doom drew straight into the framebuffer, see `update_zero_copy`
+    Direct,
+}
+
+fn draw_frame(frame: Frame) {
+    let mut fb = FB.lock();
+
+    let mode_changed = BACKEND
+        .lock()
+        .as_mut()
+        .is_some_and(|backend| backend.poll_mode(&mut fb));
+    if mode_changed {
+        let viewport = fit_viewport(&mut fb, DOOMGENERIC_RESX, DOOMGENERIC_RESY);
+        if let Some(scaler) = SCALER.lock().as_mut() {
+            scaler.set_viewport(viewport);
         }
-        // let spot = y * fb.pitch;
-        // let dg_buf_slot = y * DOOMGENERIC_RESX;
-        // fb.buf[spot..spot + PITCH].copy_from_slice(&dg_buf[dg_buf_slot..dg_buf_slot + PITCH])
+    }
+
+    if let Some(scaler) = SCALER.lock().as_mut() {
+        if let Frame::Xrgb(dg_buf) = frame {
+            if core::mem::take(&mut OPTIONS.lock().blit_bench) {
+                blit::bench(scaler, &mut fb, dg_buf);
+            }
+        }
+
+        if OPTIONS.lock().full_redraw {
+            scaler.redraw();
+        }
+
+        let blit_start = Perf::now();
+        match frame {
+            Frame::Xrgb(dg_buf) => scaler.draw(&mut fb, dg_buf),
+            Frame::Indexed(screen) => {
+                if let Some(palette) = PALETTE.lock().take() {
+                    scaler.set_palette(&palette);
+                }
+                scaler.draw_indexed(&mut fb, screen);
+            }
+            Frame::Direct => {}
+        }
+        let blit_ns = Perf::now() - blit_start;
+
+        let mut perf = PERF.lock();
+        perf.frame(blit_ns);
+        if perf.shown {
+            perf.draw(&mut fb, scaler.viewport());
+        }
+        drop(perf);
+
+        update_zero_copy(&mut fb, scaler);
+    }
+
+    let mut recorder = RECORDER.lock();
+    if let (Some(rec), Frame::Xrgb(dg_buf)) = (recorder.as_mut(), frame) {
+        if let Err(err) = rec.frame(dg_buf, DOOMGENERIC_RESX) {
+            eprintln!("video recording stopped: {err}");
+            *recorder = None;
+        }
+    }
+    drop(recorder);
+
+    let quit = BACKEND
+        .lock()
+        .as_mut()
+        .is_some_and(|backend| backend.present(&fb));
+    drop(fb);
+
+    if quit {
+        exit(0);
     }
 
     yield_now();
 }
 
+// point DG_ScreenBuffer straight at the viewport when the blit would only copy the frame,
+// or back at doom's own buffer when that stops being true (like after a mode change)
+fn update_zero_copy(fb: &mut Framebuffer, scaler: &mut Scaler) {
+    let usable = {
+        let opts = OPTIONS.lock();
+        // the bench and the recorder read DG_ScreenBuffer,
+        // and doom would draw over the overlay right before it is shown
+        opts.zero_copy && !opts.blit_bench
+    } && RECORDER.lock().is_none()
+        && !PERF.lock().shown
+        && fb.format == PixelFormat::XRGB8888
+        && fb.pitch.is_multiple_of(4)
+        && scaler.is_passthrough();
+
+    let viewport = scaler.viewport();
+    let pitch = fb.pitch;
+    let target = fb
+        .buf
+        .as_mut()
+        .filter(|_| usable)
+        .map(|buf| buf[viewport.x * 4 + viewport.y * pitch..].as_mut_ptr() as *mut u32);
+
+    let mut zero_copy = ZERO_COPY.lock();
+    match target {
+        Some(target) => {
+            if zero_copy.is_none() {
+                println!("fb: zero-copy");
+                *zero_copy = Some(unsafe { DG_ScreenBuffer } as usize);
+            }
+            unsafe {
+                DG_ScreenBuffer = target;
+                DG_ScreenPitch = (pitch / 4) as u32;
+            }
+        }
+        None => {
+            if let Some(own) = zero_copy.take() {
+                println!("fb: copying");
+                unsafe {
+                    DG_ScreenBuffer = own as *mut u32;
+                    DG_ScreenPitch = DOOMGENERIC_RESX as u32;
+                }
+                scaler.redraw();
+            }
+        }
+    }
+}
+
+#[no_mangle]
+extern "C" fn DG_Endoom(data: *const u8) {
+    let opts = OPTIONS.lock().clone();
+    if !opts.endoom {
+        return;
+    }
+    lazy_init();
+
+    let data = unsafe { slice::from_raw_parts(data, endoom::SIZE) };
+    if opts.headless.is_some() {
+        endoom::print(data);
+    }
+
+    let mut fb = FB.lock();
+    let viewport = fit_viewport(&mut fb, endoom::WIDTH, endoom::HEIGHT);
+    let mut scaler = Scaler::new(
+        viewport,
+        Filter::Nearest,
+        opts.rotation,
+        endoom::WIDTH,
+        endoom::HEIGHT,
+    );
+    let mut screen = vec![0; endoom::WIDTH * endoom::HEIGHT];
+
+    // drop the keys that were still queued from the game
+    while KEYS.pop().is_some() {}
+
+    let start = Perf::now();
+    let mut shown_blink = None;
+    loop {
+        let elapsed = Perf::now() - start;
+        let blink_off = (elapsed / endoom::BLINK_NS) % 2 == 1;
+        if shown_blink != Some(blink_off) {
+            shown_blink = Some(blink_off);
+            endoom::render(data, blink_off, &mut screen);
+            scaler.draw(&mut fb, &screen);
+            if let Some(backend) = BACKEND.lock().as_mut() {
+                backend.present(&fb);
+            }
+        }
+
+        // headless, nobody is going to press a key
+        if opts.headless.is_some() || elapsed >= endoom::TIMEOUT_NS {
+            break;
+        }
+        if core::iter::from_fn(|| KEYS.pop()).any(|ev| ev.pressed == 1) {
+            break;
+        }
+
+        nanosleep(10_000_000);
+    }
+}
+
 #[no_mangle]
 extern "C" fn DG_SleepMs(ms: u32) {
     nanosleep(ms as u64 * 1_000_000)
@@ -288,7 +480,40 @@ extern "C" fn DG_GetTicksMs() -> u32 {
 
 #[no_mangle]
 extern "C" fn DG_GetKey(_pressed: *mut ffi::c_int, _doom_key: *mut ffi::c_uchar) -> ffi::c_int {
-    if let Some(Ev { key, pressed }) = KEYS.pop() {
+    let mut typed = 0;
+    DG_GetKeyEvent(_pressed, _doom_key, &mut typed)
+}
+
+#[no_mangle]
+extern "C" fn DG_GetKeyEvent(
+    _pressed: *mut ffi::c_int,
+    _doom_key: *mut ffi::c_uchar,
+    _typed: *mut ffi::c_uchar,
+) -> ffi::c_int {
+    while let Some(Ev {
+        key,
+        pressed,
+        typed,
+        text,
+    }) = KEYS.pop()
+    {
+        if text && !text_entry() {
+            continue;
+        }
+
+        // the overlay takes both the press and the release of its key
+        if Some(key) == OPTIONS.lock().perf_key {
+            if pressed == 1 {
+                let mut perf = PERF.lock();
+                perf.shown = !perf.shown;
+                // the dirty tracking doesn't know about the overlay
+                if let Some(scaler) = SCALER.lock().as_mut() {
+                    scaler.redraw();
+                }
+            }
+            continue;
+        }
+
         // if pressed == 1 {
         //     eprintln!("{key} up");
         // } else {
@@ -298,77 +523,94 @@ extern "C" fn DG_GetKey(_pressed: *mut ffi::c_int, _doom_key: *mut ffi::c_uchar)
         unsafe {
             *_pressed = pressed;
             *_doom_key = key;
+            *_typed = typed;
         }
 
-        1
-    } else {
-        0
+        return 1;
+    }
+
+    0
+}
+
+// is doom in a menu, typing a savegame name or chatting
+fn text_entry() -> bool {
+    unsafe {
+        ptr::addr_of!(menuactive).read_volatile() != 0
+            || ptr::addr_of!(saveStringEnter).read_volatile() != 0
+            || ptr::addr_of!(chat_on).read_volatile() != 0
     }
 }
 
 #[no_mangle]
-unsafe extern "C" fn DG_SetWindowTitle(title: *const ffi::c_char) {
-    let title = unsafe { libc::as_rust_str(title) }.unwrap();
-    rename(title).unwrap();
+extern "C" fn DG_IsKeyHeld(key: ffi::c_uchar) -> ffi::c_int {
+    KEY_STATE.lock().is_held(key) as ffi::c_int
 }
 
-//
+#[no_mangle]
+extern "C" fn DG_GetMouse(
+    buttons: *mut ffi::c_int,
+    dx: *mut ffi::c_int,
+    dy: *mut ffi::c_int,
+) -> ffi::c_int {
+    let Some((b, x, y)) = MOUSE.lock().take() else {
+        return 0;
+    };
 
-fn framebuffer_info() -> Framebuffer<'static> {
-    let fbo_info = OpenOptions::new().read(true).open("/dev/fb0-info").unwrap();
-    let mut fbo_info = BufReader::new(fbo_info);
+    unsafe {
+        *buttons = b;
+        *dx = x;
+        *dy = y;
+    }
 
-    let mut buf = String::new();
-    fbo_info.read_line(&mut buf).unwrap();
-    drop(fbo_info);
+    1
+}
 
-    let mut fbo_info_iter = buf.split(':');
-    let width = fbo_info_iter.next().unwrap().parse::<usize>().unwrap();
-    let height = fbo_info_iter.next().unwrap().parse::<usize>().unwrap();
-    let pitch = fbo_info_iter.next().unwrap().parse::<usize>().unwrap();
-    // let bpp = fbo_info_iter.next().unwrap().parse::<usize>().unwrap();
+#[no_mangle]
+extern "C" fn DG_GetJoystick(
+    buttons: *mut ffi::c_int,
+    turn: *mut ffi::c_int,
+    forward: *mut ffi::c_int,
+    strafe: *mut ffi::c_int,
+) -> ffi::c_int {
+    let Some((b, x, y, s)) = GAMEPAD.lock().state() else {
+        return 0;
+    };
 
-    Framebuffer {
-        width,
-        height,
-        pitch,
-        buf: None,
+    unsafe {
+        *buttons = b;
+        *turn = x;
+        *forward = y;
+        *strafe = s;
     }
-}
 
-#[allow(unused)]
-#[derive(Debug)]
-struct Framebuffer<'a> {
-    width: usize,
-    height: usize,
-    pitch: usize,
-    buf: Option<&'a mut [u8]>,
+    1
 }
 
-impl Framebuffer<'_> {
-    fn fill(&mut self, x: usize, y: usize, w: usize, h: usize, color: Color) {
-        for yd in y..y + h {
-            let spot = x * 4 + yd * self.pitch;
-            self.buf.as_mut().unwrap()[spot..spot + 4 * w]
-                .as_chunks_mut::<4>()
-                .0
-                .fill(color.as_arr());
-        }
-    }
+#[no_mangle]
+unsafe extern "C" fn DG_SetWindowTitle(title: *const ffi::c_char) {
+    let title = unsafe { libc::as_rust_str(title) }.unwrap();
+    rename(title).unwrap();
 }
 
 //
 
+//
+
 fn main() {
     _strncmp_test();
     _strncasecmp_test();
     _atoi_test();
     _strlen_test();
+    _keymap_test();
+    _lut_parse_test();
+    _decode_test();
 
     // println!("doomgeneric_Create");
 
-    let argv = args()
-        .map(|a| CString::new(a).unwrap())
+    let args = args().collect::<Vec<String>>();
+    let argv = args
+        .iter()
+        .map(|a| CString::new(a.as_str()).unwrap())
         .collect::<Vec<CString>>();
     let c_argv = argv.iter().map(|s| s.as_ptr()).collect::<Vec<*const i8>>();
     let c_argv = c_argv.as_ptr();
@@ -376,6 +618,11 @@ fn main() {
 
     println!("argv: {argv:?}");
 
+    let opts = Options::parse(&args);
+    PERF.lock().shown = opts.perf;
+    *KEYMAP.lock() = Keymap::load(opts.keymap.as_deref());
+    *OPTIONS.lock() = opts;
+
     unsafe {
         doomgeneric_Create(c_argc as i32, c_argv);
     }
@@ -383,6 +630,8 @@ fn main() {
     // println!("doomgeneric_Tick");
 
     loop {
+        let start = Perf::now();
         unsafe { doomgeneric_Tick() };
+        PERF.lock().tick(Perf::now() - start);
     }
 }
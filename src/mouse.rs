@@ -0,0 +1,120 @@
+use alloc::{string::String, vec::Vec};
+use libstd::sync::Mutex;
+
+use crate::device::{self, Reader};
+
+//
+
+// the motion and buttons read since doom last asked for them
+pub static MOUSE: Mutex<Mouse> = Mutex::new(Mouse::new());
+
+// hyperion's mouse event stream, one json event per line like the keyboard on stdin
+pub const DEFAULT_DEVICE: &str = "/dev/mouse";
+
+// doom's ev_mouse button bits
+const BUTTON_LEFT: i32 = 1;
+const BUTTON_RIGHT: i32 = 2;
+const BUTTON_MIDDLE: i32 = 4;
+
+//
+
+#[derive(Debug)]
+pub struct Mouse {
+    sensitivity: f32,
+    invert: bool,
+
+    // the fractions left over after scaling carry into the next event
+    dx: f32,
+    dy: f32,
+    buttons: i32,
+    changed: bool,
+}
+
+// the motion since the last event, y points up
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct MouseEventSer {
+    #[serde(default)]
+    delta_x: i32,
+    #[serde(default)]
+    delta_y: i32,
+    #[serde(default)]
+    left: bool,
+    #[serde(default)]
+    middle: bool,
+    #[serde(default)]
+    right: bool,
+}
+
+impl Mouse {
+    pub const fn new() -> Self {
+        Self {
+            sensitivity: 1.0,
+            invert: false,
+            dx: 0.0,
+            dy: 0.0,
+            buttons: 0,
+            changed: false,
+        }
+    }
+
+    fn push(&mut self, ev: MouseEventSer) {
+        let buttons = if ev.left { BUTTON_LEFT } else { 0 }
+            | if ev.right { BUTTON_RIGHT } else { 0 }
+            | if ev.middle { BUTTON_MIDDLE } else { 0 };
+        let dy = if self.invert { -ev.delta_y } else { ev.delta_y };
+
+        self.dx += ev.delta_x as f32 * self.sensitivity;
+        self.dy += dy as f32 * self.sensitivity;
+        self.changed |= buttons != self.buttons || ev.delta_x != 0 || ev.delta_y != 0;
+        self.buttons = buttons;
+    }
+
+    // the button bits and the whole units of motion, if anything happened since the last call
+    pub fn take(&mut self) -> Option<(i32, i32, i32)> {
+        if !self.changed {
+            return None;
+        }
+        self.changed = false;
+
+        let (dx, dy) = (self.dx as i32, self.dy as i32);
+        self.dx -= dx as f32;
+        self.dy -= dy as f32;
+        Some((self.buttons, dx, dy))
+    }
+}
+
+// read mouse events from `device` in the background,
+// a missing default device just means there is no mouse
+pub fn spawn_reader(device: Option<String>, sensitivity: f32, invert: bool) {
+    {
+        let mut mouse = MOUSE.lock();
+        mouse.sensitivity = sensitivity;
+        mouse.invert = invert;
+    }
+
+    let optional = device.is_none();
+    let device = device.unwrap_or_else(|| DEFAULT_DEVICE.into());
+    device::spawn_reader("mouse", Some(device), optional, MouseReader::default());
+}
+
+#[derive(Default)]
+struct MouseReader {
+    pending: Vec<u8>,
+}
+
+impl Reader for MouseReader {
+    fn read(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        device::lines("mouse", &mut self.pending, |line| {
+            if let Ok(ev) = serde_json::from_str::<MouseEventSer>(line) {
+                MOUSE.lock().push(ev);
+            }
+        });
+    }
+
+    fn closed(&mut self) {
+        self.pending.clear();
+        // nobody is going to send the button releases
+        MOUSE.lock().push(MouseEventSer::default());
+    }
+}
@@ -0,0 +1,248 @@
+use alloc::string::String;
+use libstd::{eprintln, sync::Mutex};
+
+use crate::{
+    backend::ImageFormat,
+    blit::{Rotation, Scale},
+    color::GradeParams,
+    filter::Filter,
+    gamepad::DEFAULT_DEAD_ZONE,
+    input::InputFormat,
+    keymap,
+};
+
+//
+
+// doom parses its own args from argv, these are the ones only the hyperion side cares about
+pub static OPTIONS: Mutex<Options> = Mutex::new(Options::new());
+
+const KEY_F12: u8 = 0x80 + 0x58;
+
+//
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // stretch the 320x200 image vertically to 4:3, like it was on a CRT
+    pub aspect_correct: bool,
+    // `-scale <fit|N|half>`: the size of the image, N times 640x400 or 320x200 for half
+    pub scale: Scale,
+    // `-rotate <0|90|180|270>`: rotate the image clockwise, for portrait panels
+    pub rotation: Rotation,
+    // benchmark the blitter on the first frame
+    pub blit_bench: bool,
+    // redraw the whole frame every tick instead of only the parts that changed
+    pub full_redraw: bool,
+    // draw from doom's 8 bit buffer when the filter allows it, `-noindexed` disables it
+    pub indexed: bool,
+    // let doom draw straight into the framebuffer when the blit would be a plain copy,
+    // `-nozerocopy` disables it
+    pub zero_copy: bool,
+    // `-filter <nearest|scale2x|bilinear|scanlines>`
+    pub filter: Filter,
+    // `-gamma <f>`, `-brightness <f>`, `-contrast <f>`, `-saturation <f>`
+    pub grade: GradeParams,
+    // `-lut <file.cube>`: a 1D or 3D color LUT applied after the other corrections
+    pub lut: Option<String>,
+    // `-keymap <file>`: hyperion keycode to doom key overrides, `keymap.cfg` is used if it exists
+    pub keymap: Option<String>,
+    // `-input <device>`: read keyboard events from a device instead of stdin
+    pub input: Option<String>,
+    // `-input-format <binary|json>`: defaults to binary for `-input` and json for stdin
+    pub input_format: Option<InputFormat>,
+    // read the mouse, `-nomouse` disables it
+    pub mouse: bool,
+    // `-mouse <device>`: where hyperion's mouse events come from, defaults to `/dev/mouse`
+    pub mouse_device: Option<String>,
+    // `-mouse-sensitivity <f>`: scales the motion before doom's own sensitivity and acceleration
+    pub mouse_sensitivity: f32,
+    // `-mouse-invert`: moving the mouse forward walks backwards
+    pub mouse_invert: bool,
+    // read the controller, `-nogamepad` disables it
+    pub gamepad: bool,
+    // `-gamepad <device>`: where hyperion's controller events come from, defaults to `/dev/gamepad`
+    pub gamepad_device: Option<String>,
+    // `-gamepad-deadzone <f>`: the fraction of each stick's range that counts as centered
+    pub gamepad_dead_zone: f32,
+
+    // `-headless <dir>`: don't touch /dev/fb0, write the frames into `dir` instead
+    pub headless: Option<String>,
+    // `-headless-format <ppm|png>`
+    pub headless_format: ImageFormat,
+    // `-headless-every <n>`: only write every nth frame
+    pub headless_every: usize,
+    // `-headless-frames <n>`: quit after n frames
+    pub headless_frames: Option<usize>,
+    // `-headless-size <w>x<h>`
+    pub headless_size: (usize, usize),
+
+    // `-record-video <path>`: record the gameplay into a .y4m file
+    pub record_video: Option<String>,
+
+    // `-perf`: start with the performance overlay shown
+    pub perf: bool,
+    // `-perf-key <doom key|none>`: the key that toggles the overlay, it never reaches doom,
+    // defaults to F12 which is also doom's coop spy key
+    pub perf_key: Option<u8>,
+
+    // show the ENDOOM screen when quitting until a key is pressed, `-noendoom` skips it
+    pub endoom: bool,
+}
+
+impl Options {
+    pub const fn new() -> Self {
+        Self {
+            aspect_correct: false,
+            scale: Scale::Fit,
+            rotation: Rotation::R0,
+            blit_bench: false,
+            full_redraw: false,
+            indexed: true,
+            zero_copy: true,
+            filter: Filter::Nearest,
+            grade: GradeParams::NEUTRAL,
+            lut: None,
+            keymap: None,
+            input: None,
+            input_format: None,
+            mouse: true,
+            mouse_device: None,
+            mouse_sensitivity: 1.0,
+            mouse_invert: false,
+            gamepad: true,
+            gamepad_device: None,
+            gamepad_dead_zone: DEFAULT_DEAD_ZONE,
+            headless: None,
+            headless_format: ImageFormat::Ppm,
+            headless_every: 1,
+            headless_frames: None,
+            headless_size: (640, 400),
+            record_video: None,
+            perf: false,
+            perf_key: Some(KEY_F12),
+            endoom: true,
+        }
+    }
+
+    pub fn parse(args: &[String]) -> Self {
+        let mut opts = Self::new();
+
+        let mut args = args.iter().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-aspect" => opts.aspect_correct = true,
+                "-blitbench" => opts.blit_bench = true,
+                "-fullredraw" => opts.full_redraw = true,
+                "-noindexed" => opts.indexed = false,
+                "-nozerocopy" => opts.zero_copy = false,
+                "-scale" => match args.next().map(|s| Scale::parse(s)) {
+                    Some(Some(scale)) => opts.scale = scale,
+                    _ => eprintln!("-scale expects fit, half or a whole number"),
+                },
+                "-rotate" => match args.next().map(|s| Rotation::parse(s)) {
+                    Some(Some(rotation)) => opts.rotation = rotation,
+                    _ => eprintln!("-rotate expects 0, 90, 180 or 270"),
+                },
+                "-filter" => match args.next().map(|s| Filter::parse(s)) {
+                    Some(Some(filter)) => opts.filter = filter,
+                    _ => eprintln!("-filter expects one of: nearest, scale2x, bilinear, scanlines"),
+                },
+                "-gamma" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0.0 => opts.grade.gamma = n,
+                    _ => eprintln!("-gamma expects a positive number"),
+                },
+                "-brightness" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => opts.grade.brightness = n,
+                    None => eprintln!("-brightness expects a number"),
+                },
+                "-contrast" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => opts.grade.contrast = n,
+                    None => eprintln!("-contrast expects a number"),
+                },
+                "-saturation" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => opts.grade.saturation = n,
+                    None => eprintln!("-saturation expects a number"),
+                },
+                "-lut" => match args.next() {
+                    Some(path) => opts.lut = Some(path.clone()),
+                    None => eprintln!("-lut expects a .cube file"),
+                },
+                "-keymap" => match args.next() {
+                    Some(path) => opts.keymap = Some(path.clone()),
+                    None => eprintln!("-keymap expects a keymap file"),
+                },
+                "-input" => match args.next() {
+                    Some(path) => opts.input = Some(path.clone()),
+                    None => eprintln!("-input expects a device path"),
+                },
+                "-input-format" => match args.next().map(|s| InputFormat::parse(s)) {
+                    Some(Some(format)) => opts.input_format = Some(format),
+                    _ => eprintln!("-input-format expects binary or json"),
+                },
+                "-nomouse" => opts.mouse = false,
+                "-mouse" => match args.next() {
+                    Some(path) => opts.mouse_device = Some(path.clone()),
+                    None => eprintln!("-mouse expects a device path"),
+                },
+                "-mouse-sensitivity" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0.0 => opts.mouse_sensitivity = n,
+                    _ => eprintln!("-mouse-sensitivity expects a positive number"),
+                },
+                "-mouse-invert" => opts.mouse_invert = true,
+                "-nogamepad" => opts.gamepad = false,
+                "-gamepad" => match args.next() {
+                    Some(path) => opts.gamepad_device = Some(path.clone()),
+                    None => eprintln!("-gamepad expects a device path"),
+                },
+                "-gamepad-deadzone" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if (0.0..1.0).contains(&n) => opts.gamepad_dead_zone = n,
+                    _ => eprintln!("-gamepad-deadzone expects a number from 0 to 1"),
+                },
+                "-headless" => match args.next() {
+                    Some(dir) => opts.headless = Some(dir.clone()),
+                    None => eprintln!("-headless expects an output directory"),
+                },
+                "-headless-format" => match args.next().map(|s| s.as_str()) {
+                    Some("ppm") => opts.headless_format = ImageFormat::Ppm,
+                    Some("png") => opts.headless_format = ImageFormat::Png,
+                    _ => eprintln!("-headless-format expects ppm or png"),
+                },
+                "-headless-every" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => opts.headless_every = n,
+                    None => eprintln!("-headless-every expects a number"),
+                },
+                "-headless-frames" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => opts.headless_frames = Some(n),
+                    None => eprintln!("-headless-frames expects a number"),
+                },
+                "-headless-size" => match args.next().and_then(|s| parse_size(s)) {
+                    Some(size) => opts.headless_size = size,
+                    None => eprintln!("-headless-size expects <width>x<height>"),
+                },
+                "-perf" => opts.perf = true,
+                "-perf-key" => match args.next().map(|s| (s.as_str(), keymap::parse_key(s))) {
+                    Some(("none", _)) => opts.perf_key = None,
+                    Some((_, Some(key))) => opts.perf_key = Some(key),
+                    _ => eprintln!(
+                        "-perf-key expects a doomkeys.h name, a character, a number or none"
+                    ),
+                },
+                "-noendoom" => opts.endoom = false,
+                "-record-video" => match args.next() {
+                    Some(path) => opts.record_video = Some(path.clone()),
+                    None => eprintln!("-record-video expects an output path"),
+                },
+                // everything else is for doom
+                _ => {}
+            }
+        }
+
+        opts
+    }
+}
+
+// `640x400`
+fn parse_size(s: &str) -> Option<(usize, usize)> {
+    let (w, h) = s.split_once('x')?;
+    let (w, h) = (w.parse().ok()?, h.parse().ok()?);
+    (w != 0 && h != 0).then_some((w, h))
+}
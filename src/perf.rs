@@ -0,0 +1,161 @@
+use alloc::format;
+use libstd::sys::timestamp;
+
+use crate::{blit::Viewport, fb::Framebuffer, font};
+
+//
+
+extern "C" {
+    // d_loop.c, the number of game tics run so far, doom keeps writing it
+    static mut gametic: core::ffi::c_int;
+}
+
+// how often the numbers on the overlay are refreshed
+const WINDOW_NS: u64 = 1_000_000_000;
+
+const SCALE: usize = 2;
+const PADDING: usize = 4;
+const LINES: usize = 3;
+
+//
+
+// frame timing stats, optionally drawn over the top left corner of the viewport
+#[derive(Debug)]
+pub struct Perf {
+    pub shown: bool,
+
+    window_start: u64,
+    window_tics: i32,
+    last_frame: Option<u64>,
+
+    frames: u64,
+    frame_ns_sum: u64,
+    frame_ns_max: u64,
+    tick_ns_sum: u64,
+    blit_ns_sum: u64,
+
+    // the stats of the last full window
+    fps: f32,
+    tps: f32,
+    frame_ms_avg: f32,
+    frame_ms_max: f32,
+    tick_ms: f32,
+    blit_ms: f32,
+}
+
+impl Perf {
+    pub const fn new() -> Self {
+        Self {
+            shown: false,
+            window_start: 0,
+            window_tics: 0,
+            last_frame: None,
+            frames: 0,
+            frame_ns_sum: 0,
+            frame_ns_max: 0,
+            tick_ns_sum: 0,
+            blit_ns_sum: 0,
+            fps: 0.0,
+            tps: 0.0,
+            frame_ms_avg: 0.0,
+            frame_ms_max: 0.0,
+            tick_ms: 0.0,
+            blit_ms: 0.0,
+        }
+    }
+
+    pub fn now() -> u64 {
+        timestamp().unwrap() as u64
+    }
+
+    // one `doomgeneric_Tick` took `ns`, including the blit
+    pub fn tick(&mut self, ns: u64) {
+        self.tick_ns_sum += ns;
+    }
+
+    // one frame was presented and the blit took `blit_ns`
+    pub fn frame(&mut self, blit_ns: u64) {
+        let now = Self::now();
+        let tics = unsafe { core::ptr::addr_of!(gametic).read_volatile() };
+
+        if let Some(last) = self.last_frame.replace(now) {
+            let frame_ns = now - last;
+            self.frames += 1;
+            self.frame_ns_sum += frame_ns;
+            self.frame_ns_max = self.frame_ns_max.max(frame_ns);
+            self.blit_ns_sum += blit_ns;
+        } else {
+            self.window_start = now;
+            self.window_tics = tics;
+        }
+
+        let elapsed = now - self.window_start;
+        if elapsed < WINDOW_NS || self.frames == 0 {
+            return;
+        }
+
+        let ms = |ns: u64| ns as f32 / 1_000_000.0;
+        let frames = self.frames;
+        self.fps = frames as f32 * 1_000.0 / ms(elapsed);
+        self.tps = (tics - self.window_tics) as f32 * 1_000.0 / ms(elapsed);
+        self.frame_ms_avg = ms(self.frame_ns_sum / frames);
+        self.frame_ms_max = ms(self.frame_ns_max);
+        self.blit_ms = ms(self.blit_ns_sum / frames);
+        // the tick time includes the blit, DG_DrawFrame is called from inside of it
+        self.tick_ms = ms(self.tick_ns_sum.saturating_sub(self.blit_ns_sum) / frames);
+
+        self.window_start = now;
+        self.window_tics = tics;
+        self.frames = 0;
+        self.frame_ns_sum = 0;
+        self.frame_ns_max = 0;
+        self.tick_ns_sum = 0;
+        self.blit_ns_sum = 0;
+    }
+
+    pub fn draw(&self, fb: &mut Framebuffer, viewport: Viewport) {
+        // fixed width numbers keep the box the same size, so nothing is left behind when it shrinks
+        let lines = [
+            format!("FPS {:6.1} TPS {:6.1}", self.fps, self.tps),
+            format!(
+                "FRAME {:6.1} MS AVG {:6.1} MS MAX",
+                self.frame_ms_avg, self.frame_ms_max
+            ),
+            format!("TICK {:6.1} MS BLIT {:6.1} MS", self.tick_ms, self.blit_ms),
+        ];
+
+        let line_height = font::GLYPH_HEIGHT * SCALE + PADDING;
+        let width = lines
+            .iter()
+            .map(|line| font::text_size(line, SCALE).0)
+            .max()
+            .unwrap_or(0)
+            + 2 * PADDING;
+        let height = LINES * line_height + PADDING;
+
+        // redraw() only repaints the viewport, anything drawn outside of it would stay on the border
+        let (x, y) = (viewport.x, viewport.y);
+        let width = width.min(viewport.width);
+        let height = height.min(viewport.height);
+        let glyph_step = (font::GLYPH_WIDTH + 1) * SCALE;
+        let chars = (width.saturating_sub(2 * PADDING) + SCALE) / glyph_step;
+
+        let bg = fb.format.pack(0, 0, 0);
+        let fg = fb.format.pack(0xff, 0xff, 0x40);
+        fb.fill_packed(x, y, width, height, bg);
+        for (i, line) in lines.iter().enumerate() {
+            if (i + 1) * line_height > height {
+                break;
+            }
+            let line = line.get(..chars).unwrap_or(line);
+            font::draw_text(
+                fb,
+                x + PADDING,
+                y + PADDING + i * line_height,
+                SCALE,
+                line,
+                fg,
+            );
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use core::{
+    ffi::{c_char, c_int, c_void},
+    ptr, slice,
+    str::from_utf8,
+};
+
+use alloc::{boxed::Box, vec::Vec};
+use libstd::eprintln;
+
+use crate::libc::{fwrite, CFile};
+
+//
+
+pub const COLOR_TYPE_RGB: u8 = 2;
+pub const COLOR_TYPE_PALETTE: u8 = 3;
+
+// minimal PNG encoder: 8 bit RGB or paletted images, no filtering,
+// the image data is zlib wrapped but not compressed (deflate stored blocks)
+#[derive(Debug)]
+pub struct Encoder {
+    width: u32,
+    height: u32,
+    color_type: u8,
+    palette: Vec<[u8; 3]>,
+    // filter byte + pixels for each row
+    data: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new(width: u32, height: u32, color_type: u8) -> Self {
+        Self {
+            width,
+            height,
+            color_type,
+            palette: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn set_palette(&mut self, palette: &[[u8; 3]]) {
+        self.palette.clear();
+        self.palette.extend_from_slice(palette);
+    }
+
+    // the signature, IHDR and PLTE
+    pub fn header(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(b"\x89PNG\r\n\x1a\n");
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        // bit depth, color type, compression, filter, interlace
+        ihdr.extend_from_slice(&[8, self.color_type, 0, 0, 0]);
+        chunk(&mut out, b"IHDR", &ihdr);
+
+        if self.color_type == COLOR_TYPE_PALETTE {
+            let plte = self.palette.iter().flatten().copied().collect::<Vec<u8>>();
+            chunk(&mut out, b"PLTE", &plte);
+        }
+
+        out
+    }
+
+    pub fn write_row(&mut self, row: &[u8]) {
+        self.data.push(0); // filter type: none
+        self.data.extend_from_slice(row);
+    }
+
+    // the IDAT and IEND, also resets the rows for the next image
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        chunk(&mut out, b"IDAT", &zlib_stored(&self.data));
+        chunk(&mut out, b"IEND", &[]);
+        self.data.clear();
+        out
+    }
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 0xffff * 5 + 16);
+    // deflate, 32K window, no compression, no dict, check bits
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before `b` could overflow
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+static CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+//
+
+// the parts of the libpng api that v_video.c `WritePNGfile` uses for screenshots
+
+pub struct PngStruct {
+    file: *const CFile,
+    encoder: Option<Encoder>,
+    error_fn: Option<PngErrorFn>,
+}
+
+pub struct PngInfo {
+    width: u32,
+    height: u32,
+    color_type: u8,
+    palette: Vec<[u8; 3]>,
+}
+
+#[repr(C)]
+pub struct PngColor {
+    red: u8,
+    green: u8,
+    blue: u8,
+}
+
+type PngErrorFn = extern "C" fn(*mut PngStruct, *const c_char);
+
+impl PngStruct {
+    fn write(&self, data: &[u8]) {
+        if self.file.is_null() {
+            return;
+        }
+
+        if fwrite(data.as_ptr() as _, 1, data.len(), self.file) != data.len() {
+            self.error(b"write failed\0");
+        }
+    }
+
+    // `msg` is null terminated for the C callback
+    fn error(&self, msg: &'static [u8]) {
+        match self.error_fn {
+            Some(error_fn) => error_fn(self as *const _ as _, msg.as_ptr() as _),
+            None => eprintln!("libpng error: {}", from_utf8(msg).unwrap_or_default()),
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn png_create_write_struct(
+    _user_png_ver: *const c_char,
+    _error_ptr: *mut c_void,
+    error_fn: Option<PngErrorFn>,
+    _warn_fn: Option<PngErrorFn>,
+) -> *mut PngStruct {
+    Box::into_raw(Box::new(PngStruct {
+        file: ptr::null(),
+        encoder: None,
+        error_fn,
+    }))
+}
+
+#[no_mangle]
+pub extern "C" fn png_create_info_struct(_png: *mut PngStruct) -> *mut PngInfo {
+    Box::into_raw(Box::new(PngInfo {
+        width: 0,
+        height: 0,
+        color_type: 0,
+        palette: Vec::new(),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn png_destroy_write_struct(
+    png: *mut *mut PngStruct,
+    info: *mut *mut PngInfo,
+) {
+    if !png.is_null() && !unsafe { *png }.is_null() {
+        drop(unsafe { Box::from_raw(*png) });
+        unsafe { *png = ptr::null_mut() };
+    }
+    if !info.is_null() && !unsafe { *info }.is_null() {
+        drop(unsafe { Box::from_raw(*info) });
+        unsafe { *info = ptr::null_mut() };
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn png_init_io(png: *mut PngStruct, file: *const CFile) {
+    unsafe { (*png).file = file };
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn png_set_IHDR(
+    png: *mut PngStruct,
+    info: *mut PngInfo,
+    width: u32,
+    height: u32,
+    bit_depth: c_int,
+    color_type: c_int,
+    interlace: c_int,
+    _compression: c_int,
+    _filter: c_int,
+) {
+    let (png, info) = unsafe { (&*png, &mut *info) };
+
+    if bit_depth != 8
+        || interlace != 0
+        || !matches!(color_type as u8, COLOR_TYPE_RGB | COLOR_TYPE_PALETTE)
+    {
+        png.error(b"only non-interlaced 8 bit RGB or paletted images are supported\0");
+        return;
+    }
+
+    info.width = width;
+    info.height = height;
+    info.color_type = color_type as u8;
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe extern "C" fn png_set_PLTE(
+    _png: *mut PngStruct,
+    info: *mut PngInfo,
+    palette: *const PngColor,
+    num: c_int,
+) {
+    let info = unsafe { &mut *info };
+    let palette = unsafe { slice::from_raw_parts(palette, num.clamp(0, 256) as usize) };
+    info.palette = palette.iter().map(|c| [c.red, c.green, c.blue]).collect();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn png_write_info(png: *mut PngStruct, info: *mut PngInfo) {
+    let (png, info) = unsafe { (&mut *png, &*info) };
+
+    let mut encoder = Encoder::new(info.width, info.height, info.color_type);
+    encoder.set_palette(&info.palette);
+    png.write(&encoder.header());
+    png.encoder = Some(encoder);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn png_write_row(png: *mut PngStruct, row: *const u8) {
+    let png = unsafe { &mut *png };
+    let Some(encoder) = png.encoder.as_mut() else {
+        return;
+    };
+
+    let bytes_per_pixel = if encoder.color_type == COLOR_TYPE_RGB {
+        3
+    } else {
+        1
+    };
+    let row = unsafe { slice::from_raw_parts(row, encoder.width as usize * bytes_per_pixel) };
+    encoder.write_row(row);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn png_write_end(png: *mut PngStruct, _info: *mut PngInfo) {
+    let png = unsafe { &mut *png };
+    if let Some(mut encoder) = png.encoder.take() {
+        png.write(&encoder.finish());
+    }
+}
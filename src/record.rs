@@ -0,0 +1,112 @@
+use alloc::{format, vec, vec::Vec};
+use libstd::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    sys::{err::Error, timestamp},
+};
+
+//
+
+// doom runs at 35 tics per second
+const FPS: u128 = 35;
+
+// `-record-video <path>`: writes the presented frames as an uncompressed YUV4MPEG2 stream,
+// frames are repeated or dropped to keep the stream at a constant 35 fps
+pub struct Recorder {
+    file: BufWriter<File>,
+    width: usize,
+    height: usize,
+    start: Option<u128>,
+    written: u128,
+
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+impl Recorder {
+    pub fn new(path: &str, width: usize, height: usize) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .create_dirs(true)
+            .truncate(true)
+            .open(path)?;
+        let mut file = BufWriter::new(file);
+
+        // 4:2:0 chroma needs even dimensions, the odd row/column is dropped
+        let (width, height) = (width & !1, height & !1);
+        file.write_all(
+            format!("YUV4MPEG2 W{width} H{height} F{FPS}:1 Ip A1:1 C420jpeg\n").as_bytes(),
+        )?;
+
+        Ok(Self {
+            file,
+            width,
+            height,
+            start: None,
+            written: 0,
+            y: vec![0; width * height],
+            u: vec![0; width * height / 4],
+            v: vec![0; width * height / 4],
+        })
+    }
+
+    // `src` is a DG_ScreenBuffer sized XRGB8888 frame
+    pub fn frame(&mut self, src: &[u32], src_width: usize) -> Result<(), Error> {
+        let now = timestamp().unwrap() as u128;
+        let start = *self.start.get_or_insert(now);
+        let due = (now - start) * FPS / 1_000_000_000 + 1;
+        if self.written >= due {
+            return Ok(());
+        }
+
+        self.convert(src, src_width);
+
+        while self.written < due {
+            self.file.write_all(b"FRAME\n")?;
+            self.file.write_all(&self.y)?;
+            self.file.write_all(&self.u)?;
+            self.file.write_all(&self.v)?;
+            self.written += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.file.flush()
+    }
+
+    // full range BT.601 (what C420jpeg means), chroma is the average of each 2x2 block
+    fn convert(&mut self, src: &[u32], src_width: usize) {
+        let rgb = |px: u32| {
+            let [b, g, r, _] = px.to_le_bytes();
+            (r as i32, g as i32, b as i32)
+        };
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (r, g, b) = rgb(src[x + y * src_width]);
+                self.y[x + y * self.width] = ((77 * r + 150 * g + 29 * b) >> 8) as u8;
+            }
+        }
+
+        let half_width = self.width / 2;
+        for y in 0..self.height / 2 {
+            for x in 0..half_width {
+                let (mut r, mut g, mut b) = (0, 0, 0);
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let px = rgb(src[(2 * x + dx) + (2 * y + dy) * src_width]);
+                    r += px.0;
+                    g += px.1;
+                    b += px.2;
+                }
+                let (r, g, b) = (r / 4, g / 4, b / 4);
+
+                self.u[x + y * half_width] = (((-43 * r - 85 * g + 128 * b) >> 8) + 128) as u8;
+                self.v[x + y * half_width] = (((128 * r - 107 * g - 21 * b) >> 8) + 128) as u8;
+            }
+        }
+    }
+}